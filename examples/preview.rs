@@ -0,0 +1,766 @@
+// Opens a plugin's editor in a window of its own, so the web UI can be
+// developed without loading the plugin into a host:
+//
+//     cargo run --example vst-gui-preview -- target/debug/libplugin.so
+//
+// The mock mode opens the UI without any plugin. The document is an HTML
+// file, a directory with 'index.html' or a URL. The 'external.invoke' calls
+// are answered from a JSON file which maps messages (or their first words)
+// to responses; the other calls return empty strings:
+//
+//     cargo run --example vst-gui-preview -- --mock ui --responses ui.json
+//
+// With the 'hot-reload' feature a mocked directory is reloaded when its
+// files change. With the 'headless' feature the '--headless' flag runs the
+// mocked UI without a window. On Linux the preview works under Xvfb as well.
+
+extern crate serde_json;
+extern crate vst;
+extern crate vst_gui;
+
+extern crate libloading;
+#[cfg(windows)]
+extern crate winapi;
+
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::process;
+use std::ptr::null_mut;
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::Duration;
+
+use libloading::Library;
+use serde_json::Value;
+use vst::api::{AEffect, HostCallbackProc, PluginFlags};
+use vst::api::consts::VST_MAGIC;
+use vst::editor::{Editor, Rect};
+use vst::host::OpCode as HostOpCode;
+use vst::plugin::OpCode;
+
+const USAGE: &str = "\
+Usage:
+    vst-gui-preview <plugin library>
+    vst-gui-preview --mock <document> [--responses <file>] [--headless]";
+
+const TITLE: &str = "vst-gui preview";
+
+const DEFAULT_SIZE: (i32, i32) = (800, 600);
+
+// Hosts usually call 'Editor::idle' 30-60 times a second.
+const IDLE_INTERVAL: Duration = Duration::from_millis(20);
+
+struct Arguments {
+    plugin: Option<String>,
+    document: Option<String>,
+    responses: Option<String>,
+    headless: bool,
+}
+
+fn parse_arguments() -> Result<Arguments, String> {
+    let mut arguments = Arguments {
+        plugin: None,
+        document: None,
+        responses: None,
+        headless: false,
+    };
+
+    let mut values = env::args().skip(1);
+
+    while let Some(value) = values.next() {
+        match value.as_str() {
+            "--mock" => arguments.document = values.next(),
+            "--responses" => arguments.responses = values.next(),
+            "--headless" if cfg!(feature = "headless") =>
+                arguments.headless = true,
+            "--help" | "-h" => return Err(String::new()),
+            _ if value.starts_with("--") =>
+                return Err(format!("Unknown option '{}'", value)),
+            _ => arguments.plugin = Some(value),
+        }
+    }
+
+    match (&arguments.plugin, &arguments.document) {
+        (Some(_), None) if arguments.responses.is_none() &&
+            !arguments.headless => Ok(arguments),
+        (None, Some(_)) => Ok(arguments),
+        _ => Err(String::new()),
+    }
+}
+
+// The function which creates plugin instances. It's declared this way by
+// 'vst::plugin_main!' as well.
+#[allow(improper_ctypes_definitions)]
+type PluginMain = extern "C" fn(HostCallbackProc) -> *mut AEffect;
+
+// The size the hosted plugin requested with 'audioMasterSizeWindow'.
+static REQUESTED_SIZE: Mutex<Option<(i32, i32)>> = Mutex::new(None);
+
+// Receives the calls of the hosted plugin.
+fn host_callback(
+    _effect: *mut AEffect,
+    opcode: i32,
+    index: i32,
+    value: isize,
+    _ptr: *mut c_void,
+    opt: f32) -> isize
+{
+    match HostOpCode::from(opcode) {
+        HostOpCode::Version => 2400,
+        HostOpCode::Automate => {
+            println!("automate {} {}", index, opt);
+            0
+        },
+        HostOpCode::SizeWindow => {
+            *REQUESTED_SIZE.lock().unwrap() = Some((index, value as i32));
+            1
+        },
+        _ => 0,
+    }
+}
+
+// vst 0.2 doesn't pass 'Editor::idle' to the plugins it hosts, which breaks
+// everything vst-gui does on idle, and doesn't expose their 'AEffect'. So
+// the plugin is created and driven through its dispatcher directly.
+struct HostedPlugin {
+    effect: *mut AEffect,
+    size: (i32, i32),
+    is_open: bool,
+    // The library must outlive the plugin.
+    _library: Library,
+}
+
+impl HostedPlugin {
+    fn load(path: &str) -> Result<HostedPlugin, Box<dyn Error>> {
+        let library = Library::new(path)?;
+
+        let effect = unsafe {
+            let main = *library.get::<PluginMain>(b"VSTPluginMain\0")?;
+            main(host_callback)
+        };
+
+        if effect.is_null() || unsafe { (*effect).magic } != VST_MAGIC {
+            return Err("Couldn't create a plugin instance".into());
+        }
+
+        let plugin = HostedPlugin {
+            effect,
+            size: DEFAULT_SIZE,
+            is_open: false,
+            _library: library,
+        };
+
+        plugin.dispatch(OpCode::Initialize, null_mut());
+
+        let flags = unsafe { (*effect).flags };
+
+        if flags & PluginFlags::HAS_EDITOR.bits() == 0 {
+            return Err("The plugin has no editor".into());
+        }
+
+        println!("Loaded '{}'", plugin.name());
+        Ok(plugin)
+    }
+
+    fn dispatch(&self, opcode: OpCode, ptr: *mut c_void) -> isize {
+        unsafe {
+            ((*self.effect).dispatcher)(
+                self.effect,
+                opcode.into(),
+                0, /*index*/
+                0, /*value*/
+                ptr,
+                0.0 /*opt*/)
+        }
+    }
+
+    fn name(&self) -> String {
+        let mut name = [0u8; 64];
+
+        self.dispatch(OpCode::GetEffectName, name.as_mut_ptr() as *mut c_void);
+
+        let length = name.iter().position(|&byte| byte == 0).unwrap_or(0);
+        String::from_utf8_lossy(&name[..length]).into_owned()
+    }
+
+    fn editor_size(&self) -> Option<(i32, i32)> {
+        let mut rect: *mut Rect = null_mut();
+
+        self.dispatch(
+            OpCode::EditorGetRect,
+            &mut rect as *mut *mut Rect as *mut c_void);
+
+        match rect.is_null() {
+            true => None,
+            false => unsafe {
+                let rect = &*rect;
+                Some((
+                    (rect.right - rect.left) as i32,
+                    (rect.bottom - rect.top) as i32))
+            },
+        }
+    }
+}
+
+impl Editor for HostedPlugin {
+    fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn idle(&mut self) {
+        self.dispatch(OpCode::EditorIdle, null_mut());
+
+        if let Some(size) = REQUESTED_SIZE.lock().unwrap().take() {
+            self.size = size;
+        }
+    }
+
+    fn close(&mut self) {
+        if self.is_open {
+            self.dispatch(OpCode::EditorClose, null_mut());
+            self.is_open = false;
+        }
+    }
+
+    fn open(&mut self, parent: *mut c_void) -> bool {
+        self.is_open = self.dispatch(OpCode::EditorOpen, parent) != 0;
+
+        if let Some(size) = self.editor_size() {
+            self.size = size;
+        }
+
+        self.is_open
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.is_open
+    }
+}
+
+impl Drop for HostedPlugin {
+    fn drop(&mut self) {
+        self.close();
+        self.dispatch(OpCode::Shutdown, null_mut());
+    }
+}
+
+fn load_plugin(path: &str) -> Result<Box<dyn Editor>, Box<dyn Error>> {
+    Ok(Box::new(HostedPlugin::load(path)?))
+}
+
+// Answers with the response to the whole message or to its first word.
+fn scripted_callback(responses: Value) -> vst_gui::JavascriptCallback {
+    Box::new(move |message: String| {
+        let command = message.split_whitespace().next().unwrap_or("");
+
+        let response = match responses
+            .get(&message)
+            .or_else(|| responses.get(command))
+        {
+            Some(Value::String(response)) => response.clone(),
+            Some(response) => response.to_string(),
+            None => String::new(),
+        };
+
+        println!("invoke {:?} -> {:?}", message, response);
+        response
+    })
+}
+
+fn file_url(path: &Path) -> Result<String, Box<dyn Error>> {
+    let path = fs::canonicalize(path)?;
+    let path = path.to_string_lossy();
+    // Windows paths are returned in the '\\?\C:\...' form.
+    let path = path.trim_start_matches(r"\\?\").replace('\\', "/");
+
+    let mut url = String::from(
+        if path.starts_with('/') { "file://" } else { "file:///" });
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' |
+            b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+            byte => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    Ok(url)
+}
+
+fn mock_gui(arguments: &Arguments) -> Result<Box<dyn Editor>, Box<dyn Error>> {
+    let document = arguments.document.as_ref().unwrap();
+
+    let responses = match arguments.responses {
+        Some(ref path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => Value::Null,
+    };
+
+    let builder = vst_gui::PluginGuiBuilder::new()
+        .callback(scripted_callback(responses))
+        .console(|message| {
+            println!("console.{:?} {}", message.level, message.message);
+        })
+        .error_page(true);
+
+    let path = Path::new(document);
+
+    let builder = if document.contains("://") {
+        builder.url(document.as_str())
+    } else if path.is_dir() {
+        mock_directory(builder, path)?
+    } else {
+        builder.url(file_url(path)?)
+    };
+
+    #[cfg(feature = "headless")]
+    let builder = match arguments.headless {
+        true => builder.headless(),
+        false => builder,
+    };
+
+    Ok(Box::new(builder.build()?))
+}
+
+#[cfg(feature = "hot-reload")]
+fn mock_directory(
+    builder: vst_gui::PluginGuiBuilder,
+    directory: &Path) -> Result<vst_gui::PluginGuiBuilder, Box<dyn Error>>
+{
+    Ok(builder.development_directory(directory))
+}
+
+#[cfg(not(feature = "hot-reload"))]
+fn mock_directory(
+    builder: vst_gui::PluginGuiBuilder,
+    directory: &Path) -> Result<vst_gui::PluginGuiBuilder, Box<dyn Error>>
+{
+    Ok(builder.url(file_url(&directory.join("index.html"))?))
+}
+
+// Runs until the window is closed.
+fn run(mut editor: Box<dyn Editor>) -> Result<(), Box<dyn Error>> {
+    let mut window = window::Window::new(TITLE, DEFAULT_SIZE)?;
+
+    if !editor.open(window.handle()) {
+        return Err("Couldn't open the editor".into());
+    }
+
+    while window.process_events() {
+        editor.idle();
+
+        // The editor may resize itself, e.g. when the page calls
+        // 'vst.resize'.
+        let size = editor.size();
+
+        if size.0 > 0 && size.1 > 0 && size != window.size() {
+            window.resize(size);
+        }
+
+        sleep(IDLE_INTERVAL);
+    }
+
+    editor.close();
+    Ok(())
+}
+
+// Runs until the process is terminated.
+fn run_headless(mut editor: Box<dyn Editor>) -> Result<(), Box<dyn Error>> {
+    if !editor.open(null_mut()) {
+        return Err("Couldn't open the editor".into());
+    }
+
+    loop {
+        editor.idle();
+        sleep(IDLE_INTERVAL);
+    }
+}
+
+fn main() {
+    let arguments = match parse_arguments() {
+        Ok(arguments) => arguments,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("{}", message);
+            }
+
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let result = match arguments.plugin {
+        Some(ref plugin) => load_plugin(plugin),
+        None => mock_gui(&arguments),
+    };
+
+    let result = result.and_then(|editor| match arguments.headless {
+        true => run_headless(editor),
+        false => run(editor),
+    });
+
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod window {
+    use std::error::Error;
+    use std::ffi::CString;
+    use std::mem::zeroed;
+    use std::os::raw::*;
+    use std::ptr::null;
+
+    use libloading::Library;
+
+    pub enum Display {}
+
+    type XWindow = c_ulong;
+    type Atom = c_ulong;
+
+    const CLIENT_MESSAGE: c_int = 33;
+
+    // Only the client messages are read, the other events are skipped.
+    #[allow(dead_code)]
+    #[repr(C)]
+    struct ClientMessageEvent {
+        kind: c_int,
+        serial: c_ulong,
+        send_event: c_int,
+        display: *mut Display,
+        window: XWindow,
+        message_type: Atom,
+        format: c_int,
+        data: [c_long; 5],
+    }
+
+    // The size of the 'XEvent' union.
+    type Event = [c_long; 24];
+
+    #[allow(non_snake_case)]
+    struct Xlib {
+        XOpenDisplay: unsafe extern "C" fn(*const c_char) -> *mut Display,
+        XCloseDisplay: unsafe extern "C" fn(*mut Display) -> c_int,
+        XDefaultScreen: unsafe extern "C" fn(*mut Display) -> c_int,
+        XRootWindow: unsafe extern "C" fn(*mut Display, c_int) -> XWindow,
+        XCreateSimpleWindow: unsafe extern "C" fn(
+            *mut Display, XWindow, c_int, c_int, c_uint, c_uint, c_uint,
+            c_ulong, c_ulong) -> XWindow,
+        XDestroyWindow: unsafe extern "C" fn(*mut Display, XWindow) -> c_int,
+        XStoreName:
+            unsafe extern "C" fn(*mut Display, XWindow, *const c_char) -> c_int,
+        XInternAtom:
+            unsafe extern "C" fn(*mut Display, *const c_char, c_int) -> Atom,
+        XSetWMProtocols: unsafe extern "C" fn(
+            *mut Display, XWindow, *mut Atom, c_int) -> c_int,
+        XMapWindow: unsafe extern "C" fn(*mut Display, XWindow) -> c_int,
+        XResizeWindow:
+            unsafe extern "C" fn(*mut Display, XWindow, c_uint, c_uint) -> c_int,
+        XPending: unsafe extern "C" fn(*mut Display) -> c_int,
+        XNextEvent: unsafe extern "C" fn(*mut Display, *mut Event) -> c_int,
+        XFlush: unsafe extern "C" fn(*mut Display) -> c_int,
+        _library: Library,
+    }
+
+    unsafe fn symbol<T: Copy>(
+        library: &Library, name: &[u8]) -> Result<T, Box<dyn Error>>
+    {
+        Ok(*library.get::<T>(name)?)
+    }
+
+    impl Xlib {
+        fn load() -> Result<Xlib, Box<dyn Error>> {
+            let library = Library::new("libX11.so.6")?;
+
+            unsafe {
+                Ok(Xlib {
+                    XOpenDisplay: symbol(&library, b"XOpenDisplay\0")?,
+                    XCloseDisplay: symbol(&library, b"XCloseDisplay\0")?,
+                    XDefaultScreen: symbol(&library, b"XDefaultScreen\0")?,
+                    XRootWindow: symbol(&library, b"XRootWindow\0")?,
+                    XCreateSimpleWindow:
+                        symbol(&library, b"XCreateSimpleWindow\0")?,
+                    XDestroyWindow: symbol(&library, b"XDestroyWindow\0")?,
+                    XStoreName: symbol(&library, b"XStoreName\0")?,
+                    XInternAtom: symbol(&library, b"XInternAtom\0")?,
+                    XSetWMProtocols: symbol(&library, b"XSetWMProtocols\0")?,
+                    XMapWindow: symbol(&library, b"XMapWindow\0")?,
+                    XResizeWindow: symbol(&library, b"XResizeWindow\0")?,
+                    XPending: symbol(&library, b"XPending\0")?,
+                    XNextEvent: symbol(&library, b"XNextEvent\0")?,
+                    XFlush: symbol(&library, b"XFlush\0")?,
+                    _library: library,
+                })
+            }
+        }
+    }
+
+    pub struct Window {
+        xlib: Xlib,
+        display: *mut Display,
+        handle: XWindow,
+        delete_message: Atom,
+        size: (i32, i32),
+    }
+
+    impl Window {
+        pub fn new(title: &str, size: (i32, i32))
+            -> Result<Window, Box<dyn Error>>
+        {
+            let xlib = Xlib::load()?;
+            let title = CString::new(title)?;
+
+            unsafe {
+                let display = (xlib.XOpenDisplay)(null());
+
+                if display.is_null() {
+                    return Err("Couldn't open the X display".into());
+                }
+
+                let screen = (xlib.XDefaultScreen)(display);
+                let handle = (xlib.XCreateSimpleWindow)(
+                    display,
+                    (xlib.XRootWindow)(display, screen),
+                    0, /*x*/
+                    0, /*y*/
+                    size.0 as c_uint,
+                    size.1 as c_uint,
+                    0, /*border_width*/
+                    0, /*border*/
+                    0 /*background*/);
+
+                // Closing the window sends a message instead of destroying
+                // the window along with the editor.
+                let mut delete_message = (xlib.XInternAtom)(
+                    display, b"WM_DELETE_WINDOW\0".as_ptr() as *const c_char,
+                    0 /*only_if_exists*/);
+
+                (xlib.XSetWMProtocols)(display, handle, &mut delete_message, 1);
+                (xlib.XStoreName)(display, handle, title.as_ptr());
+                (xlib.XMapWindow)(display, handle);
+                (xlib.XFlush)(display);
+
+                Ok(Window {
+                    xlib,
+                    display,
+                    handle,
+                    delete_message,
+                    size,
+                })
+            }
+        }
+
+        // Hosts pass X11 window IDs instead of pointers.
+        pub fn handle(&self) -> *mut c_void {
+            self.handle as *mut c_void
+        }
+
+        pub fn size(&self) -> (i32, i32) {
+            self.size
+        }
+
+        pub fn resize(&mut self, size: (i32, i32)) {
+            unsafe {
+                (self.xlib.XResizeWindow)(
+                    self.display, self.handle, size.0 as c_uint, size.1 as c_uint);
+                (self.xlib.XFlush)(self.display);
+            }
+
+            self.size = size;
+        }
+
+        // Returns 'false' when the window is closed.
+        pub fn process_events(&mut self) -> bool {
+            unsafe {
+                while (self.xlib.XPending)(self.display) > 0 {
+                    let mut event: Event = zeroed();
+
+                    (self.xlib.XNextEvent)(self.display, &mut event);
+
+                    let message = &*(event.as_ptr() as *const ClientMessageEvent);
+
+                    if message.kind == CLIENT_MESSAGE &&
+                        message.data[0] as Atom == self.delete_message
+                    {
+                        return false;
+                    }
+                }
+            }
+
+            true
+        }
+    }
+
+    impl Drop for Window {
+        fn drop(&mut self) {
+            unsafe {
+                (self.xlib.XDestroyWindow)(self.display, self.handle);
+                (self.xlib.XCloseDisplay)(self.display);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod window {
+    use std::error::Error;
+    use std::ffi::OsStr;
+    use std::mem::zeroed;
+    use std::os::raw::c_void;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr::{null, null_mut};
+
+    use winapi::shared::minwindef::*;
+    use winapi::shared::windef::*;
+    use winapi::um::libloaderapi::GetModuleHandleW;
+    use winapi::um::winuser::*;
+
+    const STYLE: DWORD = WS_OVERLAPPEDWINDOW | WS_CLIPCHILDREN | WS_VISIBLE;
+
+    fn wide_string(value: &str) -> Vec<u16> {
+        OsStr::new(value).encode_wide().chain(Some(0)).collect()
+    }
+
+    // Returns the size of the window with the given client area size.
+    fn outer_size(size: (i32, i32)) -> (i32, i32) {
+        let mut rectangle = RECT {left: 0, top: 0, right: size.0, bottom: size.1};
+
+        unsafe {
+            AdjustWindowRect(&mut rectangle, STYLE, FALSE);
+        }
+
+        (rectangle.right - rectangle.left, rectangle.bottom - rectangle.top)
+    }
+
+    // Closing the window quits the message loop instead of destroying the
+    // window along with the editor.
+    extern "system" fn window_procedure(
+        handle: HWND, message: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT
+    {
+        unsafe {
+            match message {
+                WM_CLOSE => {
+                    PostQuitMessage(0);
+                    0
+                },
+                _ => DefWindowProcW(handle, message, wparam, lparam),
+            }
+        }
+    }
+
+    pub struct Window {
+        handle: HWND,
+        size: (i32, i32),
+    }
+
+    impl Window {
+        pub fn new(title: &str, size: (i32, i32))
+            -> Result<Window, Box<dyn Error>>
+        {
+            let class_name = wide_string("vst_gui_preview");
+            let title = wide_string(title);
+            let outer_size = outer_size(size);
+
+            let handle = unsafe {
+                let class = WNDCLASSW {
+                    style: 0,
+                    lpfnWndProc: Some(window_procedure),
+                    cbClsExtra: 0,
+                    cbWndExtra: 0,
+                    hInstance: GetModuleHandleW(null()),
+                    hIcon: null_mut(),
+                    hCursor: LoadCursorW(null_mut(), IDC_ARROW),
+                    hbrBackground: (COLOR_WINDOW + 1) as HBRUSH,
+                    lpszMenuName: null(),
+                    lpszClassName: class_name.as_ptr(),
+                };
+
+                RegisterClassW(&class);
+
+                CreateWindowExW(
+                    0, /*extended_style*/
+                    class_name.as_ptr(),
+                    title.as_ptr(),
+                    STYLE,
+                    CW_USEDEFAULT, /*x*/
+                    CW_USEDEFAULT, /*y*/
+                    outer_size.0,
+                    outer_size.1,
+                    null_mut(), /*parent*/
+                    null_mut(), /*menu*/
+                    GetModuleHandleW(null()),
+                    null_mut())
+            };
+
+            if handle.is_null() {
+                return Err("Couldn't create a window".into());
+            }
+
+            Ok(Window {
+                handle,
+                size,
+            })
+        }
+
+        pub fn handle(&self) -> *mut c_void {
+            self.handle as *mut c_void
+        }
+
+        pub fn size(&self) -> (i32, i32) {
+            self.size
+        }
+
+        pub fn resize(&mut self, size: (i32, i32)) {
+            let outer_size = outer_size(size);
+
+            unsafe {
+                SetWindowPos(
+                    self.handle,
+                    null_mut(), /*insert_after*/
+                    0, /*x*/
+                    0, /*y*/
+                    outer_size.0,
+                    outer_size.1,
+                    SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+            }
+
+            self.size = size;
+        }
+
+        // Returns 'false' when the window is closed.
+        pub fn process_events(&mut self) -> bool {
+            unsafe {
+                let mut message: MSG = zeroed();
+
+                while PeekMessageW(&mut message, null_mut(), 0, 0, PM_REMOVE)
+                    != 0
+                {
+                    if message.message == WM_QUIT {
+                        return false;
+                    }
+
+                    TranslateMessage(&message);
+                    DispatchMessageW(&message);
+                }
+            }
+
+            true
+        }
+    }
+
+    impl Drop for Window {
+        fn drop(&mut self) {
+            unsafe {
+                DestroyWindow(self.handle);
+            }
+        }
+    }
+}
@@ -1,3 +1,10 @@
+// The example keeps the code it was written with before these lints.
+#![allow(
+    clippy::collapsible_match,
+    clippy::redundant_static_lifetimes,
+    clippy::unnecessary_cast,
+    clippy::unnecessary_unwrap)]
+
 #[macro_use]
 extern crate vst;
 extern crate vst_gui;
@@ -33,6 +40,8 @@ const HTML: &'static str = r#"
             <label for="frequencyRange">Frequency</label>
             <br/>
             <input id="frequencyRange" type="range" min="55" max="880" value="440" step="any"/>
+            <br/>
+            <canvas id="oscilloscope" width="400" height="100"></canvas>
         </body>
 
         <script>
@@ -48,6 +57,27 @@ const HTML: &'static str = r#"
             frequencyRange.addEventListener("change", function(event) {
                 external.invoke("setFrequency " + event.target.value);
             });
+
+            var oscilloscope = document.getElementById("oscilloscope");
+            var context = oscilloscope.getContext("2d");
+
+            vst.onStream("waveform", function(samples) {
+                context.clearRect(0, 0, oscilloscope.width, oscilloscope.height);
+                context.beginPath();
+
+                for (var index = 0; index < samples.length; ++index) {
+                    var x = index * oscilloscope.width / samples.length;
+                    var y = (0.5 - 2.0 * samples[index]) * oscilloscope.height;
+
+                    if (index === 0) {
+                        context.moveTo(x, y);
+                    } else {
+                        context.lineTo(x, y);
+                    }
+                }
+
+                context.stroke();
+            });
         </script>
     </html>
 "#;
@@ -99,6 +129,9 @@ struct ExampleSynth {
     // We access this object both from a UI thread and from an audio processing
     // thread.
     oscillator: Arc<Mutex<Oscillator>>,
+    // Passes the output to the oscilloscope in the editor.
+    waveform: vst_gui::StreamWriter,
+    waveform_reader: Option<vst_gui::StreamReader>,
 }
 
 impl Default for ExampleSynth {
@@ -112,9 +145,13 @@ impl Default for ExampleSynth {
             }
         ));
 
+        let (waveform, waveform_reader) = vst_gui::new_stream(4096);
+
         ExampleSynth {
             sample_rate: 44100.0,
             oscillator: oscillator.clone(),
+            waveform,
+            waveform_reader: Some(waveform_reader),
         }
     }
 }
@@ -144,13 +181,14 @@ impl Plugin for ExampleSynth {
 
         let actual_phase = oscillator.phase;
         let actual_frequency = oscillator.frequency;
+        let sample_rate = self.sample_rate;
 
         let phase = |sample_index: usize| {
             actual_phase + 2.0 * PI * actual_frequency *
-                (sample_index as f32) / self.sample_rate
+                (sample_index as f32) / sample_rate
         };
 
-        for (_, output) in buffer.zip() {
+        for (channel, (_, output)) in buffer.zip().enumerate() {
             for (index, sample) in output.iter_mut().enumerate() {
                 let sine_wave = phase(index).sin();
                 let square_wave = phase(index).cos().signum();
@@ -159,16 +197,26 @@ impl Plugin for ExampleSynth {
                     sine_wave * (1.0 - oscillator.waveform) +
                     square_wave * oscillator.waveform);
             }
+
+            if channel == 0 {
+                self.waveform.write(output);
+            }
         }
 
         oscillator.phase = phase(buffer.samples()) % (2.0 * PI);
     }
 
     fn get_editor(&mut self) -> Option<Box<dyn Editor>> {
-        let gui = vst_gui::new_plugin_gui(
-            String::from(HTML),
-            create_javascript_callback(self.oscillator.clone()),
-            None);
+        let mut gui = vst_gui::PluginGuiBuilder::new()
+            .html(HTML)
+            .callback(create_javascript_callback(self.oscillator.clone()))
+            .build()
+            .ok()?;
+
+        if let Some(waveform_reader) = self.waveform_reader.take() {
+            gui.add_stream("waveform", waveform_reader, 30.0);
+        }
+
         Some(Box::new(gui))
     }
 }
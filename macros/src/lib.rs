@@ -0,0 +1,224 @@
+// Procedural macros re-exported by the 'vst-gui' crate.
+
+extern crate proc_macro;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro::TokenStream;
+
+// Parses a string literal token like '"dist"' or 'r#"dist"#'.
+fn parse_string_literal(literal: &str) -> Option<String> {
+    if let Some(raw_literal) = literal.strip_prefix('r') {
+        let hashes = raw_literal.len() - raw_literal.trim_start_matches('#').len();
+        let value = raw_literal.get(hashes + 1..raw_literal.len() - hashes - 1)?;
+        return Some(value.to_string());
+    }
+
+    let value = literal.strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut result = String::new();
+    let mut characters = value.chars();
+
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            result.push(character);
+            continue;
+        }
+
+        match characters.next()? {
+            '\\' => result.push('\\'),
+            '"' => result.push('"'),
+            '\'' => result.push('\''),
+            'n' => result.push('\n'),
+            't' => result.push('\t'),
+            _ => return None,
+        }
+    }
+
+    Some(result)
+}
+
+// Collects the files of the directory and its subdirectories as pairs of
+// relative paths and absolute paths.
+fn collect_files(
+    directory: &Path,
+    prefix: &str,
+    files: &mut Vec<(String, PathBuf)>) -> Result<(), String>
+{
+    let entries = fs::read_dir(directory)
+        .map_err(|error| format!(
+            "Couldn't read the '{}' directory: {}", directory.display(), error))?;
+
+    let mut paths = Vec::new();
+
+    for entry in entries {
+        let entry = entry.map_err(|error| error.to_string())?;
+        paths.push(entry.path());
+    }
+
+    // The order must not depend on the file system to keep builds
+    // reproducible.
+    paths.sort();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| format!(
+                "The '{}' file name is not valid UTF-8", path.display()))?
+            .to_string();
+
+        let relative_path = format!("{}{}", prefix, name);
+
+        // Unlike 'Path::is_dir', the metadata of symbolic links describes the
+        // links themselves.
+        let metadata = fs::symlink_metadata(&path)
+            .map_err(|error| format!(
+                "Couldn't read the '{}' file: {}", path.display(), error))?;
+
+        if metadata.is_dir() {
+            collect_files(&path, &format!("{}/", relative_path), files)?;
+        } else if metadata.file_type().is_symlink() && path.is_dir() {
+            // Linked directories are skipped since they may link to their
+            // parents.
+        } else {
+            files.push((relative_path, path));
+        }
+    }
+
+    Ok(())
+}
+
+fn expand_include_assets(input: TokenStream) -> Result<String, String> {
+    let tokens: Vec<String> = input
+        .into_iter()
+        .map(|token| token.to_string())
+        .collect();
+
+    let directory = match tokens.as_slice() {
+        [literal] => parse_string_literal(literal),
+        _ => None,
+    };
+
+    let directory = directory.ok_or_else(|| String::from(
+        "'include_assets!' expects a single string literal"))?;
+
+    // Relative paths are resolved like the paths of 'include_bytes!' would
+    // be if they were relative to the crate root.
+    let manifest_directory = env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| String::from("The 'CARGO_MANIFEST_DIR' variable is not set"))?;
+    let directory = Path::new(&manifest_directory).join(directory);
+
+    let mut files = Vec::new();
+    collect_files(&directory, "", &mut files)?;
+
+    // 'include_bytes!' makes Cargo rebuild the crate when an embedded file
+    // changes. Added and removed files are not tracked: a procedural macro
+    // can't declare a dependency on a directory (see the 'assets' module).
+    let entries: Vec<String> = files
+        .iter()
+        .map(|(relative_path, path)| format!(
+            "({:?}, include_bytes!({:?}) as &'static [u8])",
+            relative_path,
+            path.to_string_lossy()))
+        .collect();
+
+    Ok(format!(
+        "::vst_gui::Assets::from_static(&[{}])",
+        entries.join(", ")))
+}
+
+// Embeds the files of a directory into the binary. See the 'assets' module of
+// the 'vst-gui' crate.
+#[proc_macro]
+pub fn include_assets(input: TokenStream) -> TokenStream {
+    let code = match expand_include_assets(input) {
+        Ok(code) => code,
+        Err(message) => format!("compile_error!({:?})", message),
+    };
+
+    code.parse().expect("The generated code must be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_literals_are_parsed() {
+        let parse = |literal| parse_string_literal(literal);
+
+        assert_eq!(parse("\"dist\""), Some("dist".to_string()));
+        assert_eq!(parse(r#""front\\end \"dist\"""#),
+            Some("front\\end \"dist\"".to_string()));
+        assert_eq!(parse("r\"C:\\dist\""), Some("C:\\dist".to_string()));
+        assert_eq!(parse("r##\"a\"#b\"##"), Some("a\"#b".to_string()));
+
+        assert_eq!(parse("dist"), None);
+        assert_eq!(parse("\"\\x41\""), None);
+        assert_eq!(parse("42"), None);
+    }
+
+    #[test]
+    fn files_are_collected_recursively_in_order() {
+        let directory = env::temp_dir().join(
+            format!("vst-gui-macros-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        fs::create_dir_all(directory.join("fonts/bold")).unwrap();
+        fs::write(directory.join("main.js"), "").unwrap();
+        fs::write(directory.join("index.html"), "").unwrap();
+        fs::write(directory.join("fonts/bold/Inter.woff2"), "").unwrap();
+
+        let mut files = Vec::new();
+        let result = collect_files(&directory, "", &mut files);
+        fs::remove_dir_all(&directory).unwrap();
+        result.unwrap();
+
+        assert_eq!(
+            files,
+            vec![
+                ("fonts/bold/Inter.woff2".to_string(),
+                    directory.join("fonts/bold/Inter.woff2")),
+                ("index.html".to_string(), directory.join("index.html")),
+                ("main.js".to_string(), directory.join("main.js")),
+            ]);
+    }
+
+    #[test]
+    fn missing_directories_are_reported() {
+        let directory = env::temp_dir().join("vst-gui-macros-missing");
+
+        let mut files = Vec::new();
+        assert!(collect_files(&directory, "", &mut files).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn linked_directories_are_skipped() {
+        use std::os::unix::fs::symlink;
+
+        let directory = env::temp_dir().join(
+            format!("vst-gui-macros-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+
+        fs::create_dir_all(directory.join("images")).unwrap();
+        fs::write(directory.join("images/knob.png"), "").unwrap();
+        symlink(&directory, directory.join("images/parent")).unwrap();
+        symlink(directory.join("images/knob.png"), directory.join("knob.png"))
+            .unwrap();
+
+        let mut files = Vec::new();
+        let result = collect_files(&directory, "", &mut files);
+        fs::remove_dir_all(&directory).unwrap();
+        result.unwrap();
+
+        let paths: Vec<&str> = files
+            .iter()
+            .map(|(relative_path, _)| relative_path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["images/knob.png", "knob.png"]);
+    }
+}
@@ -0,0 +1,212 @@
+// Resources (scripts, style sheets, images, fonts, etc.) which the page
+// loads from the 'plugin://app/' URL. Since the document's base URL is
+// 'plugin://app/', relative URLs like 'main.js' refer to assets as well.
+//
+// Assets are usually embedded into the plugin binary with the
+// 'include_assets!' macro:
+//
+//     let assets = vst_gui::include_assets!("frontend/dist");
+//
+// The macro takes a path relative to the directory containing the calling
+// crate's 'Cargo.toml' and embeds every file in this directory and its
+// subdirectories. Files are named by their paths relative to the directory
+// with '/' separators (e.g. 'fonts/Inter.woff2'). Linked subdirectories are
+// skipped.
+//
+// Cargo rebuilds the crate when an embedded file changes, but not when files
+// are added to the directory or removed from it. Crates whose assets come
+// and go (e.g. the output of a bundler) should declare the directory in a
+// build script:
+//
+//     println!("cargo:rerun-if-changed=frontend/dist");
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+pub const BASE_URL: &str = "plugin://app/";
+
+#[derive(Clone, Debug)]
+pub struct Asset {
+    pub content: Cow<'static, [u8]>,
+    pub mime_type: Cow<'static, str>,
+}
+
+pub trait AssetProvider {
+    // The path is relative to 'plugin://app/' and never starts with '/'.
+    fn get(&self, path: &str) -> Option<Asset>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Assets {
+    assets: HashMap<String, Asset>,
+}
+
+impl Assets {
+    pub fn new() -> Assets {
+        Assets::default()
+    }
+
+    // Used by the 'include_assets!' macro. MIME types are guessed from file
+    // extensions.
+    pub fn from_static(files: &[(&'static str, &'static [u8])]) -> Assets {
+        let mut assets = Assets::new();
+
+        for &(path, content) in files {
+            assets.insert(path, content, mime_type(path));
+        }
+
+        assets
+    }
+
+    // Adds an asset replacing the previous one with the same path.
+    pub fn insert<C, M>(&mut self, path: &str, content: C, mime_type: M)
+        where C: Into<Cow<'static, [u8]>>,
+              M: Into<Cow<'static, str>>
+    {
+        self.assets.insert(
+            path.trim_start_matches('/').to_string(),
+            Asset {
+                content: content.into(),
+                mime_type: mime_type.into(),
+            });
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.assets.keys().map(String::as_str)
+    }
+}
+
+impl AssetProvider for Assets {
+    fn get(&self, path: &str) -> Option<Asset> {
+        self.assets.get(path).cloned()
+    }
+}
+
+// Guesses the MIME type of a file from its extension.
+pub fn mime_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "json" | "map" => "application/json",
+        "wasm" => "application/wasm",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "txt" => "text/plain",
+        "wav" => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+// Decodes the %XX escapes of a URL. Invalid escapes are left intact.
+pub fn decode_percent_escapes(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped_byte = if bytes[index] == b'%' {
+            value
+                .get(index + 1..index + 3)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped_byte {
+            Some(byte) => {
+                result.push(byte);
+                index += 3;
+            },
+            None => {
+                result.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+// Returns the asset path for URLs starting with 'plugin://app/' and for
+// relative URLs. Returns 'None' for other URLs.
+pub fn resource_path(url: &str) -> Option<String> {
+    let path = match url.strip_prefix(BASE_URL) {
+        Some(path) => path,
+        None if url.contains(':') || url.starts_with("//") => return None,
+        None => url,
+    };
+
+    let path_end = path.find(['?', '#']).unwrap_or(path.len());
+
+    Some(
+        decode_percent_escapes(&path[..path_end])
+            .trim_start_matches("./")
+            .trim_start_matches('/')
+            .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_type_depends_on_the_extension() {
+        assert_eq!(mime_type("index.html"), "text/html");
+        assert_eq!(mime_type("scripts/main.JS"), "text/javascript");
+        assert_eq!(mime_type("fonts/Inter.woff2"), "font/woff2");
+        assert_eq!(mime_type("archive.tar.gz"), "application/octet-stream");
+        assert_eq!(mime_type("LICENSE"), "application/octet-stream");
+        assert_eq!(mime_type("v1.0/LICENSE"), "application/octet-stream");
+    }
+
+    #[test]
+    fn resource_path_accepts_plugin_and_relative_urls() {
+        let path = |url| resource_path(url);
+
+        assert_eq!(path("plugin://app/main.js"), Some("main.js".to_string()));
+        assert_eq!(path("plugin://app/"), Some(String::new()));
+        assert_eq!(path("main.js"), Some("main.js".to_string()));
+        assert_eq!(path("./css/main.css"), Some("css/main.css".to_string()));
+        assert_eq!(path("/main.js"), Some("main.js".to_string()));
+
+        assert_eq!(path("https://example.com/main.js"), None);
+        assert_eq!(path("//example.com/main.js"), None);
+        assert_eq!(path("data:text/plain,main"), None);
+    }
+
+    #[test]
+    fn resource_path_strips_queries_and_decodes_escapes() {
+        assert_eq!(
+            resource_path("plugin://app/main.js?version=2#top"),
+            Some("main.js".to_string()));
+        assert_eq!(
+            resource_path("images/big%20knob.png"),
+            Some("images/big knob.png".to_string()));
+        // Invalid escapes are left intact.
+        assert_eq!(resource_path("100%.png"), Some("100%.png".to_string()));
+    }
+}
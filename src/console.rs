@@ -0,0 +1,297 @@
+// Forwards the page's console output and uncaught errors to the plugin.
+//
+// The messages are sent through 'external.invoke' and never reach the
+// application callback. They are passed to the sink set with
+// 'PluginGuiBuilder::console' or to the 'log' crate otherwise.
+
+use log::Level;
+use serde_json::Value;
+
+const MESSAGE_PREFIX: &str = "vst-gui:console ";
+
+// Installed before any other script so that errors thrown by the other
+// scripts are reported too.
+pub const SCRIPT: &str = r#"
+    (function() {
+        var console = window.console = window.console || {};
+
+        function format(value) {
+            if (typeof value === "string") {
+                return value;
+            }
+            if (value instanceof Error) {
+                // Unlike V8, WebKit doesn't include the message in the stack.
+                var text = String(value);
+                var stack = value.stack ? String(value.stack) : "";
+                return stack.indexOf(text) === 0 ? stack :
+                    (stack ? text + "\n" + stack : text);
+            }
+            try {
+                var result = JSON.stringify(value);
+                return result === undefined ? String(value) : result;
+            } catch (error) {
+                return String(value);
+            }
+        }
+
+        function send(record) {
+            try {
+                external.invoke(
+                    "vst-gui:console " + JSON.stringify(record));
+            } catch (error) {
+            }
+        }
+
+        ["debug", "log", "info", "warn", "error"].forEach(function(level) {
+            var original = console[level];
+
+            console[level] = function() {
+                send({
+                    level: level,
+                    message: Array.prototype.map.call(arguments, format)
+                        .join(" ")
+                });
+
+                if (typeof original === "function") {
+                    original.apply(console, arguments);
+                }
+            };
+        });
+
+        window.addEventListener("error", function(event) {
+            var error = event.error;
+
+            send({
+                level: "uncaught",
+                message: event.message || format(error),
+                source: event.filename || null,
+                line: event.lineno || null,
+                column: event.colno || null,
+                stack: error && error.stack ? String(error.stack) : null
+            });
+        });
+
+        window.addEventListener("unhandledrejection", function(event) {
+            var reason = event.reason;
+
+            send({
+                level: "uncaught",
+                message: "Unhandled rejection: " + format(reason),
+                stack: reason && reason.stack ? String(reason.stack) : null
+            });
+        });
+    })();
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleLevel {
+    Debug,
+    Log,
+    Info,
+    Warn,
+    Error,
+    // An exception which wasn't caught by the page or a rejected promise
+    // which wasn't handled.
+    Uncaught,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsoleMessage {
+    pub level: ConsoleLevel,
+    pub message: String,
+    // The location is available only for uncaught exceptions and only when
+    // the browser reports it.
+    pub source: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub stack: Option<String>,
+}
+
+pub type ConsoleSink = Box<dyn Fn(&ConsoleMessage)>;
+
+fn parse(record: &str) -> Option<ConsoleMessage> {
+    let record: Value = serde_json::from_str(record).ok()?;
+
+    let level = match record["level"].as_str()? {
+        "debug" => ConsoleLevel::Debug,
+        "log" => ConsoleLevel::Log,
+        "info" => ConsoleLevel::Info,
+        "warn" => ConsoleLevel::Warn,
+        "error" => ConsoleLevel::Error,
+        "uncaught" => ConsoleLevel::Uncaught,
+        _ => return None,
+    };
+
+    let string = |key: &str| record[key].as_str().map(str::to_string);
+    let number = |key: &str| record[key].as_u64().map(|value| value as u32);
+
+    Some(ConsoleMessage {
+        level,
+        message: string("message").unwrap_or_default(),
+        source: string("source"),
+        line: number("line"),
+        column: number("column"),
+        stack: string("stack"),
+    })
+}
+
+fn log_message(message: &ConsoleMessage) {
+    let level = match message.level {
+        ConsoleLevel::Debug => Level::Debug,
+        ConsoleLevel::Log | ConsoleLevel::Info => Level::Info,
+        ConsoleLevel::Warn => Level::Warn,
+        ConsoleLevel::Error | ConsoleLevel::Uncaught => Level::Error,
+    };
+
+    match (&message.source, message.line, message.column) {
+        (Some(source), Some(line), Some(column)) => log!(
+            target: "vst_gui::console",
+            level,
+            "{} ({}:{}:{})",
+            message.message,
+            source,
+            line,
+            column),
+        _ => log!(target: "vst_gui::console", level, "{}", message.message),
+    }
+
+    if let Some(ref stack) = message.stack {
+        log!(target: "vst_gui::console", Level::Debug, "{}", stack);
+    }
+}
+
+pub struct Console {
+    sink: Option<ConsoleSink>,
+}
+
+impl Console {
+    pub fn new(sink: Option<ConsoleSink>) -> Console {
+        Console { sink }
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        let record = message.strip_prefix(MESSAGE_PREFIX)?;
+
+        // Malformed messages are dropped: they can't come from the script.
+        if let Some(message) = parse(record) {
+            match self.sink {
+                Some(ref sink) => sink(&message),
+                None => log_message(&message),
+            }
+        }
+
+        Some(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    fn console() -> (Console, Rc<RefCell<Vec<ConsoleMessage>>>) {
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let sink_messages = messages.clone();
+
+        let console = Console::new(Some(Box::new(move |message| {
+            sink_messages.borrow_mut().push(message.clone());
+        })));
+
+        (console, messages)
+    }
+
+    #[test]
+    fn handle_passes_messages_to_the_sink() {
+        let (console, messages) = console();
+
+        assert_eq!(
+            console.handle(
+                "vst-gui:console {\"level\": \"warn\", \"message\": \"a 1\"}"),
+            Some(String::new()));
+        assert_eq!(
+            console.handle(
+                "vst-gui:console {\"level\": \"uncaught\", \
+                \"message\": \"boom\", \"source\": \"main.js\", \
+                \"line\": 3, \"column\": 7, \"stack\": null}"),
+            Some(String::new()));
+
+        assert_eq!(
+            *messages.borrow(),
+            vec![
+                ConsoleMessage {
+                    level: ConsoleLevel::Warn,
+                    message: "a 1".to_string(),
+                    source: None,
+                    line: None,
+                    column: None,
+                    stack: None,
+                },
+                ConsoleMessage {
+                    level: ConsoleLevel::Uncaught,
+                    message: "boom".to_string(),
+                    source: Some("main.js".to_string()),
+                    line: Some(3),
+                    column: Some(7),
+                    stack: None,
+                },
+            ]);
+    }
+
+    #[test]
+    fn handle_ignores_other_messages() {
+        let (console, messages) = console();
+
+        assert_eq!(console.handle("console {\"level\": \"log\"}"), None);
+        assert_eq!(
+            console.handle("vst-gui:console {\"level\": \"trace\"}"),
+            Some(String::new()));
+        assert_eq!(
+            console.handle("vst-gui:console not json"),
+            Some(String::new()));
+        assert!(messages.borrow().is_empty());
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn script_forwards_the_page_output() {
+        use std::ptr::null_mut;
+
+        use headless;
+        use script;
+
+        let (console, messages) = console();
+
+        let mut gui = headless::new_plugin_gui(
+            script::inject("", SCRIPT),
+            Box::new(move |message| {
+                console.handle(&message).unwrap_or_default()
+            }),
+            Default::default());
+        gui.open(null_mut()).unwrap();
+
+        gui.execute(
+            "console.log('text', 1, {a: [true]}, null, undefined);\
+            console.debug();\
+            var event = new Event('error');\
+            event.message = 'boom';\
+            event.filename = 'main.js';\
+            event.lineno = 3;\
+            event.colno = 7;\
+            window.dispatchEvent(event);").unwrap();
+
+        let messages = messages.borrow();
+        let levels: Vec<_> = messages.iter().map(|message| message.level).collect();
+
+        assert_eq!(
+            levels,
+            vec![ConsoleLevel::Log, ConsoleLevel::Debug, ConsoleLevel::Uncaught]);
+        assert_eq!(messages[0].message, "text 1 {\"a\":[true]} null undefined");
+        assert_eq!(messages[1].message, "");
+        assert_eq!(messages[2].message, "boom");
+        assert_eq!(messages[2].source.as_deref(), Some("main.js"));
+        assert_eq!((messages[2].line, messages[2].column), (Some(3), Some(7)));
+    }
+}
@@ -0,0 +1,730 @@
+// Generates editors for plugins which only need standard controls bound to
+// their parameters. A description lists the controls grouped in sections:
+//
+//     let description = Description::new()
+//         .title("Example Synth")
+//         .size(480, 240)
+//         .section("Oscillator", vec![
+//             Control::dropdown(0, &["Sine", "Square"]).label("Waveform"),
+//             Control::knob(1).label("Frequency"),
+//         ])
+//         .section("Output", vec![Control::slider(2), Control::toggle(3)]);
+//
+//     let gui = PluginGuiBuilder::new()
+//         .description(&description, plugin.get_parameter_object())
+//         .host(host)
+//         .build()?;
+//
+// Descriptions can be read from JSON documents with the same structure:
+//
+//     {
+//         "title": "Example Synth",
+//         "size": [480, 240],
+//         "sections": [{
+//             "title": "Oscillator",
+//             "controls": [
+//                 {"type": "dropdown", "parameter": 0, "label": "Waveform",
+//                  "options": ["Sine", "Square"]},
+//                 {"type": "knob", "parameter": 1, "label": "Frequency"}
+//             ]
+//         }]
+//     }
+//
+// A document with a single untitled section can list its "controls" at the
+// top level. Controls without labels display the parameter names.
+//
+// Knobs and sliders are the 'vst-knob' and 'vst-fader' elements of the
+// 'widgets' module, which is included in the page.
+//
+// The generated page talks to the parameter binding (see
+// 'PluginGuiBuilder::parameters'), which handles all its messages, reports
+// the user's gestures to the host and passes automation to the page. The
+// callback set with 'PluginGuiBuilder::callback' receives nothing from the
+// page.
+
+use serde_json::{self, json, Value};
+
+use error::{self, Error};
+use widgets;
+
+pub enum ControlKind {
+    // Changes the value when dragged up and down or scrolled.
+    Knob,
+    // A horizontal fader.
+    Slider,
+    // Switches between 0 and 1.
+    Toggle,
+    // Selects one of the options. The options are distributed evenly over the
+    // [0, 1] range, e.g. the second of three options selects 0.5.
+    Dropdown(Vec<String>),
+}
+
+pub struct Control {
+    pub kind: ControlKind,
+    // The index of the parameter displayed and changed by the control.
+    pub parameter: i32,
+    // The parameter's name is displayed when the label is 'None'.
+    pub label: Option<String>,
+}
+
+impl Control {
+    pub fn new(kind: ControlKind, parameter: i32) -> Control {
+        Control {
+            kind,
+            parameter,
+            label: None,
+        }
+    }
+
+    pub fn knob(parameter: i32) -> Control {
+        Control::new(ControlKind::Knob, parameter)
+    }
+
+    pub fn slider(parameter: i32) -> Control {
+        Control::new(ControlKind::Slider, parameter)
+    }
+
+    pub fn toggle(parameter: i32) -> Control {
+        Control::new(ControlKind::Toggle, parameter)
+    }
+
+    pub fn dropdown<S: AsRef<str>>(parameter: i32, options: &[S]) -> Control {
+        let options = options
+            .iter()
+            .map(|option| option.as_ref().to_string())
+            .collect();
+
+        Control::new(ControlKind::Dropdown(options), parameter)
+    }
+
+    pub fn label<S: Into<String>>(mut self, label: S) -> Control {
+        self.label = Some(label.into());
+        self
+    }
+
+    fn from_json(value: &Value) -> Result<Control, Error> {
+        let parameter = value
+            .get("parameter")
+            .and_then(Value::as_i64)
+            .filter(|&parameter| parameter >= 0 && parameter <= i32::MAX as i64)
+            .ok_or_else(|| error::configuration(
+                "Each control must have a non-negative 'parameter' index"))?;
+
+        let kind = match value.get("type").and_then(Value::as_str) {
+            Some("knob") => ControlKind::Knob,
+            Some("slider") => ControlKind::Slider,
+            Some("toggle") => ControlKind::Toggle,
+            Some("dropdown") => ControlKind::Dropdown(strings(
+                value.get("options"),
+                "Each dropdown must have an array of 'options'")?),
+            Some(kind) => return Err(error::configuration(
+                &format!("Unknown control type '{}'", kind))),
+            None => return Err(error::configuration(
+                "Each control must have a 'type'")),
+        };
+
+        let label = string(value, "label", "Control labels must be strings")?;
+
+        Ok(Control {
+            kind,
+            parameter: parameter as i32,
+            label,
+        })
+    }
+}
+
+pub struct Section {
+    // Empty titles are not displayed.
+    pub title: String,
+    pub controls: Vec<Control>,
+}
+
+impl Section {
+    fn from_json(value: &Value) -> Result<Section, Error> {
+        let title = string(value, "title", "Section titles must be strings")?
+            .unwrap_or_default();
+
+        let controls = value
+            .get("controls")
+            .and_then(Value::as_array)
+            .ok_or_else(|| error::configuration(
+                "Each section must have an array of 'controls'"))?
+            .iter()
+            .map(Control::from_json)
+            .collect::<Result<Vec<Control>, Error>>()?;
+
+        Ok(Section {
+            title,
+            controls,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct Description {
+    // Displayed above the sections unless it's empty.
+    pub title: String,
+    // The logical window size. By default it's the builder's default size.
+    pub size: Option<(i32, i32)>,
+    pub sections: Vec<Section>,
+}
+
+impl Description {
+    pub fn new() -> Description {
+        Description::default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Description, Error> {
+        let value: Value = serde_json::from_str(json).map_err(|error|
+            error::configuration(
+                &format!("The description is not valid JSON: {}", error)))?;
+
+        let title = string(&value, "title", "The title must be a string")?
+            .unwrap_or_default();
+
+        let size = match value.get("size") {
+            Some(size) => Some(
+                size.as_array()
+                    .filter(|size| size.len() == 2)
+                    .and_then(|size| Some((
+                        size[0].as_i64()? as i32, size[1].as_i64()? as i32)))
+                    .ok_or_else(|| error::configuration(
+                        "The size must be a [width, height] array"))?),
+            None => None,
+        };
+
+        let sections = match (value.get("sections"), value.get("controls")) {
+            (Some(sections), None) => sections
+                .as_array()
+                .ok_or_else(|| error::configuration(
+                    "The sections must be an array"))?
+                .iter()
+                .map(Section::from_json)
+                .collect::<Result<Vec<Section>, Error>>()?,
+            (None, Some(_)) => vec![Section::from_json(&value)?],
+            _ => return Err(error::configuration(
+                "The description must have either 'sections' or 'controls'")),
+        };
+
+        Ok(Description {
+            title,
+            size,
+            sections,
+        })
+    }
+
+    pub fn title<S: Into<String>>(mut self, title: S) -> Description {
+        self.title = title.into();
+        self
+    }
+
+    pub fn size(mut self, width: i32, height: i32) -> Description {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn section<S: Into<String>>(mut self, title: S, controls: Vec<Control>)
+        -> Description
+    {
+        self.sections.push(Section {
+            title: title.into(),
+            controls,
+        });
+        self
+    }
+
+    // The number of parameters which must be exposed to the page.
+    pub fn parameter_count(&self) -> i32 {
+        self.controls()
+            .map(|control| control.parameter + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn controls(&self) -> impl Iterator<Item = &Control> {
+        self.sections.iter().flat_map(|section| section.controls.iter())
+    }
+
+    // Generates the document. The controls are numbered in order since
+    // several controls can be bound to the same parameter.
+    pub fn html(&self) -> String {
+        let mut body = String::new();
+
+        if !self.title.is_empty() {
+            body.push_str(&format!("<h1>{}</h1>\n", escape(&self.title)));
+        }
+
+        let mut number = 0;
+
+        for section in &self.sections {
+            body.push_str("<div class=\"section\">\n");
+
+            if !section.title.is_empty() {
+                body.push_str(&format!(
+                    "<div class=\"section-title\">{}</div>\n",
+                    escape(&section.title)));
+            }
+
+            body.push_str("<div class=\"controls\">\n");
+
+            for control in &section.controls {
+                body.push_str(&control_html(control, number));
+                number += 1;
+            }
+
+            body.push_str("</div>\n</div>\n");
+        }
+
+        // Knobs and sliders are listed for their labels only, the widgets
+        // bind them to the parameters.
+        let controls: Vec<Value> = self.controls()
+            .map(|control| json!({
+                "kind": match control.kind {
+                    ControlKind::Knob => "knob",
+                    ControlKind::Slider => "slider",
+                    ControlKind::Toggle => "toggle",
+                    ControlKind::Dropdown(_) => "dropdown",
+                },
+                "parameter": control.parameter,
+                "options": match control.kind {
+                    ControlKind::Dropdown(ref options) => options.len(),
+                    _ => 0,
+                },
+                "labeled": control.label.is_some(),
+            }))
+            .collect();
+
+        // The JSON can't end the script element.
+        let controls = Value::Array(controls)
+            .to_string()
+            .replace("</", "<\\/");
+
+        format!(
+            "<!doctype html>\n\
+            <html>\n\
+            <head>\n\
+            <meta charset=\"utf-8\">\n\
+            <meta http-equiv=\"x-ua-compatible\" content=\"ie=edge\">\n\
+            <title>{}</title>\n\
+            <style type=\"text/css\">{}</style>\n\
+            <script>\n{}</script>\n\
+            </head>\n\
+            <body>\n\
+            {}\
+            <script>\n{}({});\n</script>\n\
+            </body>\n\
+            </html>\n",
+            escape(&self.title),
+            STYLE,
+            widgets::inline_script(),
+            body,
+            SCRIPT,
+            controls)
+    }
+}
+
+const STYLE: &str = r#"
+    body {
+        margin: 0;
+        padding: 16px;
+        background: #2b2b2b;
+        color: #dddddd;
+        font-family: sans-serif;
+        font-size: 13px;
+        -ms-user-select: none;
+        user-select: none;
+    }
+    h1 {
+        margin: 0 0 12px;
+        font-size: 16px;
+        font-weight: normal;
+    }
+    .section {
+        margin-bottom: 16px;
+    }
+    .section-title {
+        margin-bottom: 8px;
+        color: #999999;
+        font-size: 11px;
+        text-transform: uppercase;
+    }
+    .controls {
+        display: flex;
+        flex-wrap: wrap;
+    }
+    .control {
+        width: 96px;
+        margin: 0 8px 8px 0;
+        text-align: center;
+    }
+    .control-label, .control-value {
+        display: block;
+        overflow: hidden;
+        text-overflow: ellipsis;
+        white-space: nowrap;
+    }
+    .control-value {
+        margin-top: 4px;
+        color: #999999;
+        font-size: 11px;
+    }
+    .control vst-knob, .control vst-fader.vst-fader-horizontal {
+        width: 100%;
+        margin: 6px 0;
+    }
+    .toggle {
+        display: block;
+        width: 48px;
+        height: 24px;
+        margin: 18px auto;
+        border: 2px solid #666666;
+        border-radius: 12px;
+        background: #444444;
+    }
+    .toggle.on {
+        background: #4aa3ff;
+    }
+    .dropdown {
+        width: 100%;
+        margin: 18px 0;
+    }
+"#;
+
+// Binds the toggles and the dropdowns to the parameters and displays the
+// parameter names in the labels of the controls without labels.
+const SCRIPT: &str = r#"
+(function(controls) {
+    function displayText(index) {
+        var text = vst.parameters.getText(index);
+        var label = vst.parameters.getLabel(index);
+        return label ? text + " " + label : text;
+    }
+
+    // The parameter is set first so that its new text is displayed.
+    function edit(view, value) {
+        vst.beginEdit(view.index);
+        vst.setParameter(view.index, value);
+        vst.endEdit(view.index);
+        view.change(value);
+    }
+
+    var setup = {
+        toggle: function(element, view) {
+            view.render = function(value) {
+                var on = value >= 0.5;
+                element.className = on ? "toggle on" : "toggle";
+                element.setAttribute("aria-pressed", on ? "true" : "false");
+            };
+
+            element.addEventListener("click", function() {
+                edit(view, view.value >= 0.5 ? 0 : 1);
+            });
+        },
+        dropdown: function(element, view, control) {
+            var last = Math.max(control.options - 1, 1);
+
+            view.render = function(value) {
+                element.selectedIndex = Math.round(value * last);
+            };
+
+            element.addEventListener("change", function() {
+                edit(view, element.selectedIndex / last);
+            });
+        }
+    };
+
+    var views = [];
+
+    controls.forEach(function(control, number) {
+        var element = document.getElementById("control-" + number);
+        var valueElement = document.getElementById("value-" + number);
+        var index = control.parameter;
+
+        if (!control.labeled) {
+            document.getElementById("label-" + number).textContent =
+                vst.parameters.getName(index);
+        }
+
+        if (!setup[control.kind]) {
+            return;
+        }
+
+        var view = {
+            index: index,
+            value: 0,
+            render: function() {},
+            // Displays the value without notifying the plugin.
+            change: function(value) {
+                view.value = value;
+                view.render(value);
+                valueElement.textContent = displayText(index);
+            }
+        };
+
+        setup[control.kind](element, view, control);
+        views.push(view);
+    });
+
+    // The page reads the values itself when it's (re)loaded.
+    views.forEach(function(view) {
+        view.change(vst.parameters.get(view.index));
+    });
+
+    vst.parameters.addListener(function(index, value) {
+        views.forEach(function(view) {
+            if (view.index === index) {
+                view.change(value);
+            }
+        });
+    });
+})"#;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// The widgets display the values of knobs and sliders themselves.
+fn control_html(control: &Control, number: usize) -> String {
+    let widget = match control.kind {
+        ControlKind::Knob => format!(
+            "<vst-knob id=\"control-{}\" param=\"{}\"></vst-knob>",
+            number,
+            control.parameter),
+        ControlKind::Slider => format!(
+            "<vst-fader id=\"control-{}\" param=\"{}\" \
+            orientation=\"horizontal\"></vst-fader>",
+            number,
+            control.parameter),
+        ControlKind::Toggle => format!(
+            "<button class=\"toggle\" id=\"control-{}\" type=\"button\" \
+            aria-pressed=\"false\"></button>",
+            number),
+        ControlKind::Dropdown(ref options) => format!(
+            "<select class=\"dropdown\" id=\"control-{}\">{}</select>",
+            number,
+            options
+                .iter()
+                .map(|option| format!("<option>{}</option>", escape(option)))
+                .collect::<String>()),
+    };
+
+    let value = match control.kind {
+        ControlKind::Knob | ControlKind::Slider => String::new(),
+        _ => format!(
+            "<span class=\"control-value\" id=\"value-{}\"></span>",
+            number),
+    };
+
+    format!(
+        "<div class=\"control\">\
+        <span class=\"control-label\" id=\"label-{0}\">{1}</span>\
+        {2}{3}\
+        </div>\n",
+        number,
+        control.label.as_ref().map(|label| escape(label)).unwrap_or_default(),
+        widget,
+        value)
+}
+
+// Returns 'None' if the field is missing.
+fn string(value: &Value, key: &str, message: &str)
+    -> Result<Option<String>, Error>
+{
+    match value.get(key) {
+        Some(Value::String(string)) => Ok(Some(string.clone())),
+        Some(_) => Err(error::configuration(message)),
+        None => Ok(None),
+    }
+}
+
+fn strings(value: Option<&Value>, message: &str) -> Result<Vec<String>, Error> {
+    value
+        .and_then(Value::as_array)
+        .filter(|values| !values.is_empty())
+        .and_then(|values| values
+            .iter()
+            .map(|value| value.as_str().map(String::from))
+            .collect())
+        .ok_or_else(|| error::configuration(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_message(json: &str) -> String {
+        match Description::from_json(json) {
+            Err(Error::Configuration { message }) => message,
+            Err(error) => panic!("Unexpected error {:?}", error),
+            Ok(_) => panic!("'{}' was accepted", json),
+        }
+    }
+
+    #[test]
+    fn from_json_reads_sections() {
+        let description = Description::from_json(r#"{
+            "title": "Example Synth",
+            "size": [480, 240],
+            "sections": [{
+                "title": "Oscillator",
+                "controls": [
+                    {"type": "dropdown", "parameter": 0, "label": "Waveform",
+                     "options": ["Sine", "Square"]},
+                    {"type": "knob", "parameter": 3}
+                ]
+            }, {
+                "controls": [{"type": "toggle", "parameter": 1}]
+            }]
+        }"#).unwrap();
+
+        assert_eq!(description.title, "Example Synth");
+        assert_eq!(description.size, Some((480, 240)));
+        assert_eq!(description.sections.len(), 2);
+        assert_eq!(description.parameter_count(), 4);
+
+        let oscillator = &description.sections[0];
+        assert_eq!(oscillator.title, "Oscillator");
+        assert_eq!(oscillator.controls[0].label, Some("Waveform".to_string()));
+
+        match oscillator.controls[0].kind {
+            ControlKind::Dropdown(ref options) =>
+                assert_eq!(options, &["Sine", "Square"]),
+            _ => panic!("The first control is not a dropdown"),
+        }
+
+        match oscillator.controls[1].kind {
+            ControlKind::Knob => assert_eq!(oscillator.controls[1].label, None),
+            _ => panic!("The second control is not a knob"),
+        }
+
+        assert_eq!(description.sections[1].title, "");
+    }
+
+    #[test]
+    fn from_json_reads_top_level_controls() {
+        let description = Description::from_json(
+            r#"{"controls": [{"type": "slider", "parameter": 2}]}"#).unwrap();
+
+        assert_eq!(description.title, "");
+        assert_eq!(description.size, None);
+        assert_eq!(description.sections.len(), 1);
+        assert_eq!(description.sections[0].controls[0].parameter, 2);
+    }
+
+    #[test]
+    fn from_json_rejects_invalid_descriptions() {
+        assert!(error_message("[").starts_with("The description is not valid"));
+        assert!(error_message("{}").contains("either 'sections' or"));
+        assert!(error_message(r#"{"sections": [], "controls": []}"#)
+            .contains("either 'sections' or"));
+        assert!(error_message(r#"{"title": 1, "controls": []}"#)
+            .contains("title"));
+        assert!(error_message(r#"{"size": [480], "controls": []}"#)
+            .contains("size"));
+        assert!(error_message(r#"{"controls": [{"type": "knob"}]}"#)
+            .contains("parameter"));
+        assert!(error_message(
+            r#"{"controls": [{"type": "knob", "parameter": -1}]}"#)
+            .contains("parameter"));
+        assert!(error_message(
+            r#"{"controls": [{"type": "wheel", "parameter": 0}]}"#)
+            .contains("Unknown control type 'wheel'"));
+        assert!(error_message(
+            r#"{"controls": [{"type": "dropdown", "parameter": 0}]}"#)
+            .contains("options"));
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn html_binds_the_controls() {
+        use std::ptr::null_mut;
+        use std::sync::{Arc, Mutex};
+
+        use vst::editor::Editor;
+        use vst::plugin::PluginParameters;
+
+        use PluginGuiBuilder;
+
+        struct Parameters {
+            values: Mutex<Vec<f32>>,
+        }
+
+        impl PluginParameters for Parameters {
+            fn get_parameter(&self, index: i32) -> f32 {
+                self.values.lock().unwrap()[index as usize]
+            }
+
+            fn set_parameter(&self, index: i32, value: f32) {
+                self.values.lock().unwrap()[index as usize] = value;
+            }
+
+            fn get_parameter_name(&self, index: i32) -> String {
+                format!("Parameter {}", index)
+            }
+
+            fn get_parameter_text(&self, index: i32) -> String {
+                format!("{:.2}", self.get_parameter(index))
+            }
+        }
+
+        let parameters = Arc::new(Parameters {
+            values: Mutex::new(vec![0.25, 0.75, 0.0, 0.5]),
+        });
+        let description = Description::new()
+            .section("Controls", vec![
+                Control::knob(0).label("Gain"),
+                Control::slider(1),
+                Control::toggle(2),
+                Control::dropdown(3, &["A", "B", "C"]),
+            ]);
+
+        let mut gui = PluginGuiBuilder::new()
+            .description(&description, parameters.clone())
+            .headless()
+            .build()
+            .unwrap();
+
+        assert!(gui.open(null_mut()));
+        gui.idle();
+
+        assert_eq!(
+            gui.evaluate(
+                "[0, 1, 2, 3].map(function(number) {\
+                    return document.getElementById('label-' + number)\
+                        .textContent;\
+                })").unwrap(),
+            json!(["", "Parameter 1", "Parameter 2", "Parameter 3"]));
+
+        // The widgets display the values of knobs and sliders.
+        assert_eq!(
+            gui.evaluate(
+                "[0, 1].map(function(number) {\
+                    var control = document.getElementById('control-' + number);\
+                    return [\
+                        control.vstWidget,\
+                        control.children[control.children.length - 1]\
+                            .textContent\
+                    ];\
+                })").unwrap(),
+            json!([[true, "0.25"], [true, "0.75"]]));
+
+        gui.execute(
+            "document.getElementById('control-2').click();\
+            var knob = document.getElementById('control-0');\
+            knob.children[0].dispatchEvent(new Event('dblclick'));").unwrap();
+
+        assert_eq!(parameters.get_parameter(0), 0.0);
+        assert_eq!(parameters.get_parameter(2), 1.0);
+
+        assert_eq!(
+            gui.evaluate(
+                "[document.getElementById('control-2').className,\
+                document.getElementById('value-2').textContent,\
+                document.getElementById('control-3').selectedIndex]").unwrap(),
+            json!(["toggle on", "1.00", 1]));
+    }
+}
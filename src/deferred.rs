@@ -0,0 +1,241 @@
+// Asynchronous calls from the page: 'external.invokeAsync(message)' returns a
+// promise which is settled when the plugin completes the 'Responder' passed
+// to the asynchronous callback.
+//
+// Request IDs are assigned here and never reused, so responses to requests
+// made by a previous page are ignored by the next one. Responses are passed
+// through the 'GuiHandle' queue and reach the page on 'Editor::idle'.
+
+use std::cell::Cell;
+
+use serde::Serialize;
+
+use error::Error;
+use events;
+use handle::GuiHandle;
+
+const MESSAGE_PREFIX: &str = "vst-gui:async ";
+
+// The function is also available as 'vst.invokeAsync'. On Windows the
+// 'external' object forwards 'invokeAsync' calls to the function assigned to
+// the property. Internet Explorer needs a 'Promise' polyfill.
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    (function() {
+        var pending = {};
+
+        vst.invokeAsync = function(message) {
+            if (typeof Promise === "undefined") {
+                throw new Error(
+                    "external.invokeAsync requires a Promise polyfill");
+            }
+            return new Promise(function(resolve, reject) {
+                var id = external.invoke("vst-gui:async " + message);
+                pending[id] = { resolve: resolve, reject: reject };
+            });
+        };
+        vst.settle = function(id, fulfilled, value) {
+            var request = pending[id];
+
+            if (request) {
+                delete pending[id];
+
+                if (fulfilled) {
+                    request.resolve(value);
+                } else {
+                    request.reject(new Error(value));
+                }
+            }
+        };
+
+        try {
+            external.invokeAsync = vst.invokeAsync;
+        } catch (error) {
+        }
+    })();
+"#;
+
+pub type AsyncCallback = Box<dyn Fn(String, Responder)>;
+
+// Settles the promise returned by 'external.invokeAsync'. It can be sent to
+// another thread. Dropping it without a response rejects the promise.
+pub struct Responder {
+    id: u64,
+    handle: Option<GuiHandle>,
+}
+
+impl Responder {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn settle(&mut self, fulfilled: bool, value: &str) -> bool {
+        let handle = match self.handle.take() {
+            Some(handle) => handle,
+            None => return false,
+        };
+
+        handle.execute(&format!(
+            "vst.settle({}, {}, {});", self.id, fulfilled, value))
+    }
+
+    // Resolves the promise with the serialized value. Returns 'Ok(false)' if
+    // the response is discarded right away (see 'GuiHandle::execute').
+    pub fn resolve<T: Serialize + ?Sized>(
+        mut self, value: &T) -> Result<bool, Error>
+    {
+        match events::to_javascript(value) {
+            Ok(value) => Ok(self.settle(true, &value)),
+            Err(error) => {
+                self.reject(&error.to_string());
+                Err(error)
+            }
+        }
+    }
+
+    // Rejects the promise with an 'Error' with the specified message.
+    pub fn reject(mut self, message: &str) -> bool {
+        let message = events::to_javascript(message)
+            .expect("Strings are always serializable");
+
+        self.settle(false, &message)
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            self.settle(false, "\"The request was dropped\"");
+        }
+    }
+}
+
+pub struct Requests {
+    callback: AsyncCallback,
+    handle: GuiHandle,
+    last_id: Cell<u64>,
+}
+
+impl Requests {
+    pub fn new(callback: AsyncCallback, handle: GuiHandle) -> Requests {
+        Requests {
+            callback,
+            handle,
+            last_id: Cell::new(0),
+        }
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        let message = message.strip_prefix(MESSAGE_PREFIX)?;
+
+        let id = self.last_id.get() + 1;
+        self.last_id.set(id);
+
+        (self.callback)(
+            message.to_string(),
+            Responder {
+                id,
+                handle: Some(self.handle.clone()),
+            });
+
+        Some(id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use handle::Queue;
+
+    #[test]
+    fn handle_assigns_request_ids() {
+        let queue = Queue::new();
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let callback_messages = messages.clone();
+
+        let requests = Requests::new(
+            Box::new(move |message, responder: Responder| {
+                callback_messages.borrow_mut().push((message, responder.id()));
+            }),
+            queue.handle());
+
+        assert_eq!(requests.handle("vst-gui:async load"), Some("1".to_string()));
+        assert_eq!(requests.handle("vst-gui:async save"), Some("2".to_string()));
+        assert_eq!(requests.handle("load"), None);
+
+        assert_eq!(
+            *messages.borrow(),
+            vec![("load".to_string(), 1), ("save".to_string(), 2)]);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn responders_settle_promises_after_the_callback_returns() {
+        use std::ptr::null_mut;
+        use std::thread;
+
+        use events::Events;
+        use headless;
+        use script;
+
+        let queue = Queue::new();
+        let responders = Rc::new(RefCell::new(Vec::new()));
+        let callback_responders = responders.clone();
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let page_messages = messages.clone();
+
+        let requests = Requests::new(
+            Box::new(move |_, responder| {
+                callback_responders.borrow_mut().push(responder);
+            }),
+            queue.handle());
+
+        let mut gui = headless::new_plugin_gui(
+            script::inject("", SCRIPT),
+            Box::new(move |message| {
+                requests.handle(&message).unwrap_or_else(|| {
+                    page_messages.borrow_mut().push(message);
+                    String::new()
+                })
+            }),
+            Default::default());
+        gui.open(null_mut()).unwrap();
+
+        gui.execute(
+            "vst.invokeAsync('load').then(function(value) {\
+                external.invoke('resolved ' + JSON.stringify(value));\
+            });\
+            vst.invokeAsync('save').catch(function(error) {\
+                external.invoke('rejected ' + error.message);\
+            });").unwrap();
+
+        let (loaded, saved) = {
+            let mut responders = responders.borrow_mut();
+            assert_eq!(responders.len(), 2);
+            let saved = responders.pop().unwrap();
+            (responders.pop().unwrap(), saved)
+        };
+
+        thread::spawn(move || {
+            assert!(loaded.resolve(&["preset", "1"]).unwrap());
+            drop(saved);
+        }).join().unwrap();
+
+        gui.idle();
+        assert!(messages.borrow().is_empty());
+
+        queue.drain(&*gui, &Events::new());
+        gui.idle();
+
+        assert_eq!(
+            *messages.borrow(),
+            vec![
+                "resolved [\"preset\",\"1\"]",
+                "rejected The request was dropped",
+            ]);
+    }
+}
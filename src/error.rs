@@ -0,0 +1,102 @@
+use std::env;
+use std::error;
+use std::fmt;
+
+// Codes are the platform's error codes: 'HRESULT' values on Windows and
+// 'GError' codes on Linux.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    // The builder received an invalid combination of options.
+    Configuration { message: String },
+    // The web engine or the window couldn't be created.
+    BackendInitialization { message: String, code: Option<i32> },
+    // The page couldn't be created or the document couldn't be loaded into
+    // it.
+    DocumentLoading { message: String, code: Option<i32> },
+    // The script threw an exception or the engine refused to run it.
+    ScriptEvaluation { message: String, code: Option<i32> },
+    // The operation requires an open window.
+    WindowClosed,
+    // The channel between the page and the plugin ('external.invoke' and
+    // the 'plugin' URL scheme) couldn't be set up.
+    Bridge { message: String, code: Option<i32> },
+    // A value couldn't be serialized to be passed to the page.
+    Serialization { message: String },
+    // The host didn't accept the new size of the window.
+    ResizeRejected,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let (description, message, code) = match *self {
+            Error::Configuration { ref message } =>
+                ("Invalid configuration", message.as_str(), None),
+            Error::BackendInitialization { ref message, code } =>
+                ("Couldn't initialize the backend", message.as_str(), code),
+            Error::DocumentLoading { ref message, code } =>
+                ("Couldn't load the document", message.as_str(), code),
+            Error::ScriptEvaluation { ref message, code } =>
+                ("Couldn't evaluate the script", message.as_str(), code),
+            Error::WindowClosed =>
+                return formatter.write_str("The plugin window is closed"),
+            Error::Bridge { ref message, code } =>
+                ("Couldn't set up the bridge", message.as_str(), code),
+            Error::Serialization { ref message } =>
+                ("Couldn't serialize the value", message.as_str(), None),
+            Error::ResizeRejected =>
+                return formatter.write_str(
+                    "The host refused to resize the window"),
+        };
+
+        write!(formatter, "{}: {}", description, message)?;
+
+        match code {
+            Some(code) => write!(formatter, " (code {:#x})", code),
+            None => Ok(()),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Serialization { message: error.to_string() }
+    }
+}
+
+// The text of the error page.
+pub fn report(error: &Error) -> String {
+    format!(
+        "The plugin editor couldn't be opened.\n\n{}\n\n\
+        Diagnostic information:\n\
+        vst-gui {} ({}-{}): {:?}",
+        error,
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH,
+        error)
+}
+
+// Shortcuts for errors without codes.
+
+pub fn configuration(message: &str) -> Error {
+    Error::Configuration { message: message.to_string() }
+}
+
+pub fn initialization(message: &str) -> Error {
+    Error::BackendInitialization { message: message.to_string(), code: None }
+}
+
+pub fn loading(message: &str) -> Error {
+    Error::DocumentLoading { message: message.to_string(), code: None }
+}
+
+pub fn evaluation(message: &str) -> Error {
+    Error::ScriptEvaluation { message: message.to_string(), code: None }
+}
+
+#[cfg(any(windows, feature = "headless"))]
+pub fn bridge(message: &str) -> Error {
+    Error::Bridge { message: message.to_string(), code: None }
+}
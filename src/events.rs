@@ -0,0 +1,348 @@
+// Delivers events emitted by 'PluginGui::emit' to the handlers registered in
+// the page with 'vst.on(name, handler)' and tracks the page's lifecycle.
+//
+// Events and scripts passed to 'PluginGui::execute' are queued until the
+// page reports that its DOM is loaded and are delivered in order once it
+// does. The queue survives closing the window so that events emitted while
+// it's closed reach the next page. The page reports unloading as well, so
+// reloaded pages are handled like new ones.
+//
+// Pages which never report their DOM (Internet Explorer before version 9
+// doesn't support 'DOMContentLoaded', a script may throw before the report)
+// are considered loaded once their 'document.readyState' is "complete".
+
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use error::Error;
+use lib::PluginGui;
+
+// The oldest events are discarded when the page doesn't receive them for a
+// long time (e.g. when the window is closed). Scripts passed to
+// 'PluginGui::execute' are never discarded.
+const MAX_QUEUED_EVENTS: usize = 1024;
+
+// The state of a loading page is checked after this delay, so that the
+// previous document isn't mistaken for the one being loaded.
+const READY_CHECK_DELAY: Duration = Duration::from_secs(1);
+
+// A warning is logged when scripts wait for the page longer than this.
+const QUEUE_WARNING_DELAY: Duration = Duration::from_secs(10);
+
+const READY_MESSAGE: &str = "vst-gui:events.ready";
+const UNLOAD_MESSAGE: &str = "vst-gui:events.unload";
+
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    (function() {
+        var handlers = {};
+
+        vst.on = function(name, handler) {
+            (handlers[name] = handlers[name] || []).push(handler);
+        };
+        vst.off = function(name, handler) {
+            var list = handlers[name] || [];
+            var index = list.indexOf(handler);
+
+            if (index !== -1) {
+                list.splice(index, 1);
+            }
+        };
+        vst.dispatch = function(name, payload) {
+            var list = (handlers[name] || []).slice();
+
+            for (var index = 0; index < list.length; ++index) {
+                list[index](payload);
+            }
+        };
+
+        function ready() {
+            external.invoke("vst-gui:events.ready");
+        }
+
+        if (document.readyState === "loading") {
+            document.addEventListener("DOMContentLoaded", ready);
+        } else {
+            ready();
+        }
+
+        window.addEventListener("pagehide", function() {
+            external.invoke("vst-gui:events.unload");
+        });
+    })();
+"#;
+
+// Serializes a value to a Javascript expression.
+pub fn to_javascript<T: Serialize + ?Sized>(
+    value: &T) -> Result<String, Error>
+{
+    // JSON strings may contain line separators which are not allowed in
+    // Javascript strings before ES2019.
+    Ok(serde_json::to_string(value)?
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029"))
+}
+
+// Returns Javascript code dispatching the event.
+pub fn dispatch_code<T: Serialize + ?Sized>(
+    event_name: &str, payload: &T) -> Result<String, Error>
+{
+    Ok(format!(
+        "vst.dispatch({}, {});",
+        to_javascript(event_name)?,
+        to_javascript(payload)?))
+}
+
+struct Queued {
+    javascript_code: String,
+    // Events are discarded when the queue is full, scripts are not.
+    is_event: bool,
+    // Sent through a 'GuiHandle' which counts the discarded messages.
+    forwarded: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PageState {
+    Closed,
+    Loading,
+    Ready,
+}
+
+pub struct Events {
+    state: Cell<PageState>,
+    // When the current page started loading.
+    loading_start: Cell<Option<Instant>>,
+    // Whether the queue held for too long was reported for the current page.
+    reported_waiting: Cell<bool>,
+    // Lifecycle changes which were not reported with 'take_loaded' and
+    // 'take_unloaded' yet.
+    loaded: Cell<bool>,
+    unloaded: Cell<bool>,
+    // Events and scripts waiting for the page.
+    queue: RefCell<VecDeque<Queued>>,
+    queued_events: Cell<usize>,
+    // The forwarded messages discarded since the last 'take_dropped' call.
+    dropped: Cell<usize>,
+}
+
+impl Events {
+    pub fn new() -> Events {
+        Events {
+            state: Cell::new(PageState::Closed),
+            loading_start: Cell::new(None),
+            reported_waiting: Cell::new(false),
+            loaded: Cell::new(false),
+            unloaded: Cell::new(false),
+            queue: RefCell::new(VecDeque::new()),
+            queued_events: Cell::new(0),
+            dropped: Cell::new(0),
+        }
+    }
+
+    // Called before the window is opened.
+    pub fn open(&self) {
+        self.start_loading();
+        self.loaded.set(false);
+        self.unloaded.set(false);
+    }
+
+    // Called before the window is closed or when the page can't be loaded.
+    pub fn close(&self) {
+        self.state.set(PageState::Closed);
+        self.loaded.set(false);
+        self.unloaded.set(false);
+    }
+
+    // Called before a new document is loaded into the window.
+    pub fn reload(&self) {
+        if self.state.get() != PageState::Closed {
+            self.start_loading();
+            self.unloaded.set(true);
+        }
+    }
+
+    fn start_loading(&self) {
+        self.state.set(PageState::Loading);
+        self.loading_start.set(Some(Instant::now()));
+        self.reported_waiting.set(false);
+    }
+
+    // Called on idle. Marks the page loaded if it's loaded but didn't report
+    // it and warns about the scripts held for too long.
+    pub fn check_loading(&self, gui: &dyn PluginGui) {
+        let loading_time = match self.loading_start.get() {
+            Some(loading_start) if self.is_loading() => loading_start.elapsed(),
+            _ => return,
+        };
+
+        if loading_time < READY_CHECK_DELAY {
+            return;
+        }
+
+        let is_complete = gui
+            .evaluate("document.readyState")
+            .map(|ready_state| ready_state == "complete")
+            .unwrap_or(false);
+
+        if is_complete {
+            warn!(
+                "The page didn't report that its DOM is loaded. The queued \
+                scripts and events are delivered anyway.");
+            self.state.set(PageState::Ready);
+            self.loaded.set(true);
+            return;
+        }
+
+        let queue_length = self.queue.borrow().len();
+
+        if loading_time >= QUEUE_WARNING_DELAY &&
+            queue_length != 0 &&
+            !self.reported_waiting.replace(true)
+        {
+            warn!(
+                "{} scripts and events are waiting for the page which is \
+                loading for {} seconds",
+                queue_length,
+                loading_time.as_secs());
+        }
+    }
+
+    // Returns 'true' if the page is loaded.
+    pub fn is_ready(&self) -> bool {
+        self.state.get() == PageState::Ready
+    }
+
+    // Returns 'true' if the window is open but the page is not loaded yet.
+    pub fn is_loading(&self) -> bool {
+        self.state.get() == PageState::Loading
+    }
+
+    // Returns 'true' once after the page is loaded.
+    pub fn take_loaded(&self) -> bool {
+        self.loaded.replace(false)
+    }
+
+    // Returns 'true' once after the page is unloaded in the open window.
+    pub fn take_unloaded(&self) -> bool {
+        self.unloaded.replace(false)
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        if message != READY_MESSAGE && message != UNLOAD_MESSAGE {
+            return None;
+        }
+
+        // Closing the window unloads the page as well.
+        if self.state.get() == PageState::Closed {
+            return Some(String::new());
+        }
+
+        // The queue is flushed on idle: the page is not guaranteed to accept
+        // new scripts while it's calling us.
+        if message == READY_MESSAGE {
+            self.state.set(PageState::Ready);
+            self.loaded.set(true);
+        } else {
+            self.start_loading();
+            self.unloaded.set(true);
+        }
+
+        Some(String::new())
+    }
+
+    pub fn emit<T: Serialize + ?Sized>(
+        &self,
+        gui: &dyn PluginGui,
+        event_name: &str,
+        payload: &T) -> Result<(), Error>
+    {
+        self.push(gui, dispatch_code(event_name, payload)?);
+        Ok(())
+    }
+
+    // Queues the code returned by 'dispatch_code'.
+    pub fn push(&self, gui: &dyn PluginGui, javascript_code: String) {
+        self.enqueue(gui, javascript_code, true, false);
+    }
+
+    // Queues a script which the page runs in order with the events.
+    pub fn push_script(&self, gui: &dyn PluginGui, javascript_code: String) {
+        self.enqueue(gui, javascript_code, false, false);
+    }
+
+    // Like 'push' and 'push_script' for messages sent through a 'GuiHandle'.
+    pub fn forward(
+        &self, gui: &dyn PluginGui, javascript_code: String, is_event: bool)
+    {
+        self.enqueue(gui, javascript_code, is_event, true);
+    }
+
+    // Returns the number of forwarded messages discarded since the last
+    // call.
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.replace(0)
+    }
+
+    fn enqueue(
+        &self,
+        gui: &dyn PluginGui,
+        javascript_code: String,
+        is_event: bool,
+        forwarded: bool)
+    {
+        if is_event && self.queued_events.get() == MAX_QUEUED_EVENTS {
+            let mut queue = self.queue.borrow_mut();
+
+            let oldest_event = queue.iter().position(|queued| queued.is_event);
+
+            if let Some(queued) = oldest_event.and_then(|i| queue.remove(i)) {
+                self.queued_events.set(self.queued_events.get() - 1);
+                self.count_dropped(&queued);
+            }
+        }
+
+        if is_event {
+            self.queued_events.set(self.queued_events.get() + 1);
+        }
+
+        self.queue.borrow_mut().push_back(
+            Queued { javascript_code, is_event, forwarded });
+
+        self.flush(gui);
+    }
+
+    fn count_dropped(&self, queued: &Queued) {
+        if queued.forwarded {
+            self.dropped.set(self.dropped.get() + 1);
+        }
+    }
+
+    pub fn flush(&self, gui: &dyn PluginGui) {
+        if !self.is_ready() {
+            return;
+        }
+
+        // The queue must not be borrowed while the page runs scripts since
+        // the page can call us back.
+        loop {
+            let queued = match self.queue.borrow_mut().pop_front() {
+                Some(queued) => queued,
+                None => break,
+            };
+
+            if queued.is_event {
+                self.queued_events.set(self.queued_events.get() - 1);
+            }
+
+            // An event which the page fails to handle is discarded: passing
+            // it again would most likely fail again.
+            if gui.execute(&queued.javascript_code).is_err() {
+                self.count_dropped(&queued);
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+// A thread-safe handle which passes updates to the GUI from other threads
+// (e.g. from the audio thread).
+//
+// Messages are put into a bounded queue which is drained when the host calls
+// 'Editor::idle'. Sending never blocks: messages which don't fit into the
+// queue are counted as overflowed and discarded.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use error::Error;
+use events::{self, Events};
+use lib::PluginGui;
+
+const QUEUE_CAPACITY: usize = 1024;
+
+enum Message {
+    Execute(String),
+    // Javascript code dispatching an event.
+    Event(String),
+}
+
+#[derive(Default)]
+struct Counters {
+    overflowed: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HandleStatistics {
+    // The number of messages discarded because the queue was full.
+    pub overflowed: usize,
+    // The number of messages discarded because the window was closed, the
+    // page didn't receive them for a long time or their execution failed.
+    pub dropped: usize,
+}
+
+#[derive(Clone)]
+pub struct GuiHandle {
+    sender: SyncSender<Message>,
+    counters: Arc<Counters>,
+}
+
+impl GuiHandle {
+    fn send(&self, message: Message) -> bool {
+        match self.sender.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.counters.overflowed.fetch_add(1, Ordering::Relaxed);
+                false
+            },
+            // The GUI is destroyed.
+            Err(TrySendError::Disconnected(_)) => {
+                self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+                false
+            },
+        }
+    }
+
+    // Schedules a 'PluginGui::execute' call. Returns 'false' if the script
+    // is discarded right away.
+    pub fn execute(&self, javascript_code: &str) -> bool {
+        self.send(Message::Execute(javascript_code.to_string()))
+    }
+
+    // Schedules a 'PluginGui::emit' call. The payload is serialized on the
+    // calling thread. Returns 'Ok(false)' if the event is discarded right
+    // away.
+    pub fn emit<T: Serialize + ?Sized>(
+        &self, event_name: &str, payload: &T) -> Result<bool, Error>
+    {
+        let javascript_code = events::dispatch_code(event_name, payload)?;
+        Ok(self.send(Message::Event(javascript_code)))
+    }
+
+    pub fn statistics(&self) -> HandleStatistics {
+        HandleStatistics {
+            overflowed: self.counters.overflowed.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// The receiving side of the queue owned by 'PluginGui'.
+pub struct Queue {
+    handle: GuiHandle,
+    receiver: Receiver<Message>,
+}
+
+impl Queue {
+    pub fn new() -> Queue {
+        let (sender, receiver) = sync_channel(QUEUE_CAPACITY);
+
+        Queue {
+            handle: GuiHandle {
+                sender,
+                counters: Arc::new(Counters::default()),
+            },
+            receiver,
+        }
+    }
+
+    pub fn handle(&self) -> GuiHandle {
+        self.handle.clone()
+    }
+
+    pub fn drain(&self, gui: &dyn PluginGui, events: &Events) {
+        // Messages sent while draining are left for the next call.
+        let pending: Vec<Message> = self.receiver.try_iter().collect();
+
+        for message in pending {
+            match message {
+                Message::Execute(javascript_code) => {
+                    if events.is_loading() {
+                        events.forward(gui, javascript_code, false);
+                        continue;
+                    }
+
+                    // Scripts queued while the page was loading run first.
+                    events.flush(gui);
+
+                    if gui.execute(&javascript_code).is_err() {
+                        self.handle.counters.dropped.fetch_add(
+                            1, Ordering::Relaxed);
+                    }
+                },
+                Message::Event(javascript_code) =>
+                    events.forward(gui, javascript_code, true),
+            }
+        }
+    }
+
+    // Counts the messages which 'events' discarded after 'drain' passed
+    // them.
+    pub fn count_dropped(&self, events: &Events) {
+        self.handle.counters.dropped.fetch_add(
+            events.take_dropped(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn overflowed_messages_are_counted() {
+        let queue = Queue::new();
+        let handle = queue.handle();
+
+        let sent = thread::spawn(move || {
+            (0..QUEUE_CAPACITY + 3)
+                .filter(|_| handle.execute("update();"))
+                .count()
+        });
+
+        assert_eq!(sent.join().unwrap(), QUEUE_CAPACITY);
+        assert_eq!(
+            queue.handle().statistics(),
+            HandleStatistics { overflowed: 3, dropped: 0 });
+    }
+
+    #[test]
+    fn messages_sent_after_the_gui_is_destroyed_are_dropped() {
+        let handle = Queue::new().handle();
+
+        assert!(!handle.execute("update();"));
+        assert!(!handle.emit("update", &1).unwrap());
+        assert_eq!(
+            handle.statistics(),
+            HandleStatistics { overflowed: 0, dropped: 2 });
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn drain_executes_the_messages() {
+        use std::cell::RefCell;
+        use std::ptr::null_mut;
+        use std::rc::Rc;
+
+        use headless;
+
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let page_messages = messages.clone();
+
+        let mut gui = headless::new_plugin_gui(
+            String::new(),
+            Box::new(move |message| {
+                page_messages.borrow_mut().push(message);
+                String::new()
+            }),
+            Default::default());
+        let events = Events::new();
+        let queue = Queue::new();
+        let handle = queue.handle();
+
+        // Scripts fail while the window is closed.
+        handle.execute("external.invoke('lost');");
+        queue.drain(&*gui, &events);
+        assert_eq!(handle.statistics().dropped, 1);
+
+        gui.open(null_mut()).unwrap();
+        handle.execute("external.invoke('first');");
+        handle.execute("external.invoke('second');");
+        queue.drain(&*gui, &events);
+
+        assert_eq!(*messages.borrow(), vec!["first", "second"]);
+        assert_eq!(handle.statistics().dropped, 1);
+    }
+}
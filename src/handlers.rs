@@ -0,0 +1,159 @@
+// Named handlers: 'external.<name>(message)' calls the handler registered
+// with 'PluginGuiBuilder::handler' and returns its result.
+//
+// Backends pass the calls to the common callback as messages with the
+// handler name. The Windows backend recognizes the names itself because its
+// 'external' object doesn't accept new properties; other backends define the
+// functions with a script. Calling an unregistered name throws a 'TypeError'
+// in the page like calling any other missing method.
+
+use std::collections::HashMap;
+
+use error::{self, Error};
+use events;
+use lib::JavascriptCallback;
+
+const MESSAGE_PREFIX: &str = "vst-gui:handler ";
+
+// Names of the 'external' object's own methods.
+const RESERVED_NAMES: [&str; 2] = ["invoke", "invokeAsync"];
+
+// Returns the message passed to the common callback by 'external.<name>'.
+#[cfg(windows)]
+pub fn message(name: &str, message: &str) -> String {
+    format!("{}{} {}", MESSAGE_PREFIX, name, message)
+}
+
+pub fn script(names: &[String]) -> String {
+    format!(
+        r#"
+        (function(names) {{
+            names.forEach(function(name) {{
+                try {{
+                    external[name] = function(message) {{
+                        return external.invoke(
+                            "{}" + name + " " + message);
+                    }};
+                }} catch (error) {{
+                }}
+            }});
+        }})({});
+        "#,
+        MESSAGE_PREFIX,
+        events::to_javascript(names).expect("Strings are always serializable"))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut characters = name.chars();
+
+    let is_start = |character: char|
+        character.is_ascii_alphabetic() || character == '_' || character == '$';
+
+    match characters.next() {
+        Some(character) if is_start(character) => characters.all(|character|
+            is_start(character) || character.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+pub struct Handlers {
+    handlers: HashMap<String, JavascriptCallback>,
+}
+
+impl Handlers {
+    pub fn new(
+        handlers: Vec<(String, JavascriptCallback)>) -> Result<Handlers, Error>
+    {
+        let mut result = HashMap::new();
+
+        for (name, handler) in handlers {
+            if !is_identifier(&name) {
+                return Err(error::configuration(&format!(
+                    "The handler name '{}' is not a valid identifier", name)));
+            }
+
+            if RESERVED_NAMES.contains(&name.as_str()) {
+                return Err(error::configuration(&format!(
+                    "The handler name '{}' is reserved", name)));
+            }
+
+            if result.insert(name.clone(), handler).is_some() {
+                return Err(error::configuration(&format!(
+                    "The handler '{}' is registered twice", name)));
+            }
+        }
+
+        Ok(Handlers { handlers: result })
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.handlers.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        let call = message.strip_prefix(MESSAGE_PREFIX)?;
+
+        let (name, message) = match call.find(' ') {
+            Some(index) => (&call[..index], &call[index + 1..]),
+            None => (call, ""),
+        };
+
+        match self.handlers.get(name) {
+            Some(handler) => Some(handler(message.to_string())),
+            // The page can't send such messages through 'external.<name>'.
+            None => {
+                warn!("The page called the unknown handler '{}'", name);
+                Some(String::new())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handler(name: &str) -> (String, JavascriptCallback) {
+        let reply = format!("{} called", name);
+        (name.to_string(), Box::new(move |message| {
+            format!("{} with '{}'", reply, message)
+        }))
+    }
+
+    fn is_rejected(handlers: Vec<(String, JavascriptCallback)>) -> bool {
+        matches!(
+            Handlers::new(handlers), Err(Error::Configuration { .. }))
+    }
+
+    #[test]
+    fn new_validates_names() {
+        let handlers = Handlers::new(vec![handler("save"), handler("$load_2")])
+            .unwrap();
+        assert_eq!(handlers.names(), vec!["$load_2", "save"]);
+
+        assert!(is_rejected(vec![handler("")]));
+        assert!(is_rejected(vec![handler("2save")]));
+        assert!(is_rejected(vec![handler("save-as")]));
+        assert!(is_rejected(vec![handler("invoke")]));
+        assert!(is_rejected(vec![handler("invokeAsync")]));
+        assert!(is_rejected(vec![handler("save"), handler("save")]));
+    }
+
+    #[test]
+    fn handle_calls_the_named_handler() {
+        let handlers = Handlers::new(vec![handler("save")]).unwrap();
+
+        assert_eq!(
+            handlers.handle("vst-gui:handler save a b"),
+            Some("save called with 'a b'".to_string()));
+        assert_eq!(
+            handlers.handle("vst-gui:handler save"),
+            Some("save called with ''".to_string()));
+        assert_eq!(
+            handlers.handle("vst-gui:handler load x"), Some(String::new()));
+        assert_eq!(handlers.handle("save x"), None);
+    }
+}
@@ -0,0 +1,134 @@
+// A minimal HTML scanner. It extracts only what the headless implementation
+// needs from a document: the elements with their attributes and the scripts.
+
+pub struct Element {
+    pub tag_name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+pub enum Script {
+    Inline(String),
+    // The value of the 'src' attribute.
+    External(String),
+}
+
+pub struct Document {
+    pub elements: Vec<Element>,
+    pub scripts: Vec<Script>,
+}
+
+fn parse_tag(tag: &str) -> (String, Vec<(String, String)>) {
+    let tag = tag.trim_end_matches('/');
+
+    let name_end = tag
+        .find(|character: char| character.is_whitespace())
+        .unwrap_or(tag.len());
+    let name = tag[..name_end].to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    let mut rest = tag[name_end..].trim_start();
+
+    while !rest.is_empty() {
+        let attribute_name_end = rest
+            .find(|character: char|
+                character.is_whitespace() || character == '=')
+            .unwrap_or(rest.len());
+        let attribute_name = rest[..attribute_name_end].to_ascii_lowercase();
+
+        rest = rest[attribute_name_end..].trim_start();
+
+        let value = if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start();
+            let quote = value.chars().next().filter(|character|
+                *character == '"' || *character == '\'');
+
+            let (value, remainder) = match quote {
+                Some(quote) => {
+                    let value = &value[1..];
+                    let value_end = value.find(quote).unwrap_or(value.len());
+                    (&value[..value_end],
+                        value.get(value_end + 1..).unwrap_or(""))
+                },
+                None => {
+                    let value_end = value
+                        .find(|character: char| character.is_whitespace())
+                        .unwrap_or(value.len());
+                    (&value[..value_end], &value[value_end..])
+                }
+            };
+
+            rest = remainder.trim_start();
+            value.to_string()
+        } else {
+            String::new()
+        };
+
+        if !attribute_name.is_empty() {
+            attributes.push((attribute_name, value));
+        }
+    }
+
+    (name, attributes)
+}
+
+pub fn parse(html_document: &str) -> Document {
+    let mut document = Document {
+        elements: Vec::new(),
+        scripts: Vec::new(),
+    };
+
+    let mut rest = html_document;
+
+    while let Some(tag_start) = rest.find('<') {
+        rest = &rest[tag_start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment
+                .find("-->")
+                .map(|comment_end| &comment[comment_end + 3..])
+                .unwrap_or("");
+            continue;
+        }
+
+        let tag_end = match rest.find('>') {
+            Some(tag_end) => tag_end,
+            None => break,
+        };
+
+        let tag = &rest[1..tag_end];
+        rest = &rest[tag_end + 1..];
+
+        if tag.starts_with('/') || tag.starts_with('!') {
+            continue;
+        }
+
+        let (tag_name, attributes) = parse_tag(tag);
+
+        if tag_name == "script" {
+            // ASCII lowercasing preserves byte offsets.
+            let script_end = rest
+                .to_ascii_lowercase()
+                .find("</script")
+                .unwrap_or(rest.len());
+
+            let source = attributes
+                .iter()
+                .find(|(name, _)| name == "src")
+                .map(|(_, value)| value.clone());
+
+            document.scripts.push(match source {
+                Some(source) => Script::External(source),
+                None => Script::Inline(rest[..script_end].to_string()),
+            });
+
+            rest = &rest[script_end..];
+        }
+
+        // The DOM replacement has its own 'html', 'head' and 'body'.
+        if !["script", "html", "head", "body"].contains(&tag_name.as_str()) {
+            document.elements.push(Element { tag_name, attributes });
+        }
+    }
+
+    document
+}
@@ -0,0 +1,658 @@
+use std::fs;
+use std::os::raw::c_void;
+use std::ptr::null_mut;
+use std::rc::Rc;
+
+use rquickjs::{
+    CatchResultExt, CaughtError, Context, Function, Object, Runtime};
+
+use assets::{self, AssetProvider};
+use headless::document::{self, Script};
+use error::{self, Error};
+use events;
+use lib::{JavascriptCallback, Options, PluginGui};
+use script;
+
+// Used when no window size is specified since there's no screen to measure.
+const DEFAULT_SIZE: (i32, i32) = (800, 600);
+
+// A minimal replacement for the DOM. Elements of the document are added to
+// its body with '__createElement' (the nesting is not preserved), events are
+// dispatched synchronously and bubble up the tree, 'setTimeout' callbacks run
+// on the next idle call. Nothing is rendered, so elements have no size.
+const DOM_SCRIPT: &str = r##"
+    (function(global) {
+        function Event(type, init) {
+            this.type = type;
+            this.target = null;
+            this.currentTarget = null;
+            this.bubbles = !!(init && init.bubbles);
+            this.defaultPrevented = false;
+            this.propagationStopped = false;
+        }
+
+        Event.prototype.initEvent = function(type, bubbles) {
+            this.type = type;
+            this.bubbles = !!bubbles;
+        };
+
+        Event.prototype.initCustomEvent = function(
+            type, bubbles, cancelable, detail)
+        {
+            this.initEvent(type, bubbles);
+            this.detail = detail;
+        };
+
+        Event.prototype.preventDefault = function() {
+            this.defaultPrevented = true;
+        };
+
+        Event.prototype.stopPropagation = function() {
+            this.propagationStopped = true;
+        };
+
+        function CustomEvent(type, init) {
+            Event.call(this, type, init);
+            this.detail = init && init.detail !== undefined ?
+                init.detail : null;
+        }
+
+        CustomEvent.prototype = Object.create(Event.prototype);
+
+        function EventTarget() {
+            this.listeners = {};
+        }
+
+        EventTarget.prototype.addEventListener = function(type, listener) {
+            (this.listeners[type] = this.listeners[type] || []).push(listener);
+        };
+
+        EventTarget.prototype.removeEventListener = function(type, listener) {
+            var listeners = this.listeners[type] || [];
+            var index = listeners.indexOf(listener);
+
+            if (index !== -1) {
+                listeners.splice(index, 1);
+            }
+        };
+
+        EventTarget.prototype.dispatchEvent = function(event) {
+            event.target = event.target || this;
+            event.currentTarget = this;
+
+            var listeners = (this.listeners[event.type] || []).slice();
+
+            for (var index = 0; index < listeners.length; ++index) {
+                listeners[index].call(this, event);
+            }
+
+            var handler = this["on" + event.type];
+
+            if (typeof handler === "function") {
+                handler.call(this, event);
+            }
+
+            if (event.bubbles && !event.propagationStopped &&
+                this.parentNode)
+            {
+                this.parentNode.dispatchEvent(event);
+            }
+
+            return !event.defaultPrevented;
+        };
+
+        // Calls the function for the descendants of the node in the document
+        // order until it returns 'true'.
+        function find(node, predicate) {
+            for (var index = 0; index < node.childNodes.length; ++index) {
+                var child = node.childNodes[index];
+
+                if (child.nodeType === 1 &&
+                    (predicate(child) || find(child, predicate)))
+                {
+                    return true;
+                }
+            }
+
+            return false;
+        }
+
+        function getElementsByTagName(tagName) {
+            var name = tagName.toUpperCase();
+            var result = [];
+
+            find(this, function(element) {
+                if (name === "*" || element.tagName === name) {
+                    result.push(element);
+                }
+            });
+
+            return result;
+        }
+
+        function Node() {
+            EventTarget.call(this);
+
+            this.parentNode = null;
+            this.childNodes = [];
+        }
+
+        Node.prototype = Object.create(EventTarget.prototype);
+
+        Node.prototype.appendChild = function(child) {
+            if (child.parentNode) {
+                child.parentNode.removeChild(child);
+            }
+
+            child.parentNode = this;
+            this.childNodes.push(child);
+            return child;
+        };
+
+        Node.prototype.removeChild = function(child) {
+            var index = this.childNodes.indexOf(child);
+
+            if (index !== -1) {
+                this.childNodes.splice(index, 1);
+                child.parentNode = null;
+            }
+
+            return child;
+        };
+
+        Node.prototype.getElementsByTagName = getElementsByTagName;
+
+        function Text(data) {
+            this.nodeType = 3;
+            this.parentNode = null;
+            this.data = this.textContent = String(data);
+        }
+
+        function Element(tagName, attributes) {
+            Node.call(this);
+
+            this.nodeType = 1;
+            this.tagName = tagName.toUpperCase();
+            this.attributes = attributes;
+            this.id = attributes.id || "";
+            this.className = attributes["class"] || "";
+            this.value = attributes.value !== undefined ? attributes.value : "";
+            this.checked = attributes.checked !== undefined;
+            this.textContent = "";
+            this.innerHTML = "";
+            this.style = {};
+            this.offsetWidth = 0;
+            this.offsetHeight = 0;
+        }
+
+        Element.prototype = Object.create(Node.prototype);
+
+        Object.defineProperty(Element.prototype, "children", {
+            get: function() {
+                return this.childNodes.filter(function(node) {
+                    return node.nodeType === 1;
+                });
+            }
+        });
+
+        Element.prototype.getAttribute = function(name) {
+            if (name === "class") {
+                return this.className;
+            }
+
+            return this.attributes[name] !== undefined ?
+                this.attributes[name] : null;
+        };
+
+        Element.prototype.setAttribute = function(name, value) {
+            if (name === "class") {
+                this.className = String(value);
+            } else {
+                this.attributes[name] = String(value);
+            }
+        };
+
+        Element.prototype.getBoundingClientRect = function() {
+            return {
+                left: 0, top: 0, right: 0, bottom: 0, width: 0, height: 0
+            };
+        };
+
+        Element.prototype.click = function() {
+            this.dispatchEvent(new Event("click", {bubbles: true}));
+        };
+
+        var document = new Node();
+        var timeouts = [];
+
+        global.Event = Event;
+        global.CustomEvent = CustomEvent;
+        global.window = global;
+        // Reflects the zoom like in a browser.
+        global.devicePixelRatio = 1;
+        global.listeners = {};
+
+        for (var key in EventTarget.prototype) {
+            global[key] = EventTarget.prototype[key];
+        }
+
+        document.nodeType = 9;
+        document.readyState = "loading";
+        document.documentElement = document.appendChild(
+            new Element("html", {}));
+        document.head = document.documentElement.appendChild(
+            new Element("head", {}));
+        document.body = document.documentElement.appendChild(
+            new Element("body", {}));
+        document.createElement = function(tagName) {
+            return new Element(tagName, {});
+        };
+        document.createTextNode = function(data) {
+            return new Text(data);
+        };
+        document.createEvent = function() {
+            return new CustomEvent("");
+        };
+        document.getElementById = function(id) {
+            var result = null;
+
+            find(document, function(element) {
+                if (element.id === id) {
+                    result = element;
+                    return true;
+                }
+            });
+
+            return result;
+        };
+        document.querySelector = function(selector) {
+            if (selector.charAt(0) === "#") {
+                return document.getElementById(selector.slice(1));
+            }
+
+            return document.getElementsByTagName(selector)[0] || null;
+        };
+        global.document = document;
+
+        global.setTimeout = function(callback) {
+            var parameters = Array.prototype.slice.call(arguments, 2);
+            return timeouts.push(function() {
+                callback.apply(global, parameters);
+            });
+        };
+        global.clearTimeout = function(id) {
+            if (id > 0 && id <= timeouts.length) {
+                timeouts[id - 1] = null;
+            }
+        };
+
+        global.atob = function(data) {
+            var alphabet = "ABCDEFGHIJKLMNOPQRSTUVWXYZ" +
+                "abcdefghijklmnopqrstuvwxyz0123456789+/";
+            var result = "";
+            var value = 0;
+            var bits = 0;
+
+            data = String(data).replace(/[\s=]/g, "");
+
+            for (var index = 0; index < data.length; ++index) {
+                value = (value << 6) | alphabet.indexOf(data.charAt(index));
+                bits += 6;
+
+                if (bits >= 8) {
+                    bits -= 8;
+                    result += String.fromCharCode((value >> bits) & 0xff);
+                }
+            }
+
+            return result;
+        };
+
+        global.__createElement = function(tagName, attributes) {
+            document.body.appendChild(new Element(tagName, attributes));
+        };
+        global.__runTimeouts = function() {
+            var pending = timeouts;
+            timeouts = [];
+
+            for (var index = 0; index < pending.length; ++index) {
+                if (pending[index]) {
+                    pending[index]();
+                }
+            }
+        };
+        // Dispatches an uncaught exception as an 'error' event like a browser
+        // does.
+        global.__reportError = function(error, filename) {
+            var event = new Event("error");
+            event.error = error;
+            event.message = String(error);
+            event.filename = filename || "";
+
+            try {
+                global.dispatchEvent(event);
+            } catch (error) {
+            }
+        };
+        global.__finishLoading = function() {
+            document.readyState = "complete";
+
+            [[document, "DOMContentLoaded"], [global, "load"]].forEach(
+                function(target) {
+                    try {
+                        target[0].dispatchEvent(new Event(target[1]));
+                    } catch (error) {
+                        global.__reportError(error);
+                    }
+                });
+        };
+    })(this);
+"##;
+
+// Converts a string to a Javascript string literal.
+fn javascript_string(value: &str) -> String {
+    events::to_javascript(value).expect("Strings are always serializable")
+}
+
+// Returns the path from a 'file://' URL.
+fn file_path(url: &str) -> Option<String> {
+    url.strip_prefix("file://").map(assets::decode_percent_escapes)
+}
+
+struct Engine {
+    // The context must be dropped before the runtime.
+    context: Context,
+    runtime: Runtime,
+}
+
+impl Engine {
+    fn new(
+        html_document: &str,
+        js_callback: Rc<JavascriptCallback>,
+        assets: Option<&dyn AssetProvider>,
+        zoom: f64) -> Result<Engine, Error>
+    {
+        let runtime = Runtime::new()
+            .map_err(|_| error::initialization(
+                "Couldn't create a Javascript runtime"))?;
+        let context = Context::full(&runtime)
+            .map_err(|_| error::initialization(
+                "Couldn't create a Javascript context"))?;
+
+        context
+            .with(|context| -> rquickjs::Result<()> {
+                let external = Object::new(context.clone())?;
+                let invoke = Function::new(
+                    context.clone(),
+                    move |message: String| js_callback(message))?;
+
+                external.set("invoke", invoke)?;
+                context.globals().set("external", external)
+            })
+            .map_err(|error| error::bridge(&error.to_string()))?;
+
+        let engine = Engine {
+            context,
+            runtime,
+        };
+
+        engine.execute(DOM_SCRIPT).map_err(|_| error::initialization(
+            "Couldn't install the DOM replacement"))?;
+        engine.set_zoom(zoom)?;
+        engine.load_html_document(html_document, assets)?;
+
+        Ok(engine)
+    }
+
+    fn load_html_document(
+        &self,
+        html_document: &str,
+        assets: Option<&dyn AssetProvider>) -> Result<(), Error>
+    {
+        let document = document::parse(html_document);
+
+        for element in document.elements {
+            let attributes: Vec<String> = element.attributes
+                .iter()
+                .map(|(name, value)|
+                    format!(
+                        "{}: {}",
+                        javascript_string(name),
+                        javascript_string(value)))
+                .collect();
+
+            self.execute(
+                &format!(
+                    "__createElement({}, {{{}}});",
+                    javascript_string(&element.tag_name),
+                    attributes.join(", ")))?;
+        }
+
+        for script in document.scripts {
+            match script {
+                Script::Inline(script) => self.run_script(&script, None)?,
+                Script::External(source) => {
+                    // There's no network stack so only assets can be loaded.
+                    // Other scripts are skipped like the ones a browser
+                    // fails to download.
+                    let asset = assets::resource_path(&source)
+                        .and_then(|path| assets?.get(&path));
+
+                    if let Some(asset) = asset {
+                        self.run_script(
+                            &String::from_utf8_lossy(&asset.content),
+                            Some(&source))?;
+                    }
+                },
+            }
+        }
+
+        self.execute("__finishLoading();")
+    }
+
+    // Runs a script of the document. An exception doesn't stop the loading:
+    // it's dispatched as an 'error' event, which the console forwarding
+    // reports, and the next scripts run as usual.
+    fn run_script(
+        &self, javascript_code: &str, source: Option<&str>) -> Result<(), Error>
+    {
+        self.context.with(|context| {
+            let exception = match context
+                .eval::<(), _>(javascript_code)
+                .catch(&context)
+            {
+                Ok(()) => return Ok(()),
+                Err(CaughtError::Exception(exception)) =>
+                    exception.into_value(),
+                Err(CaughtError::Value(value)) => value,
+                Err(error) => return Err(
+                    error::evaluation(error.to_string().trim_end())),
+            };
+
+            context
+                .globals()
+                .get::<_, Function>("__reportError")
+                .and_then(|report| report.call((exception, source)))
+                .map_err(|error| error::evaluation(&error.to_string()))
+        })
+    }
+
+    fn set_zoom(&self, zoom: f64) -> Result<(), Error> {
+        self.execute(&format!("window.devicePixelRatio = {};", zoom))
+    }
+
+    fn idle(&self) {
+        // Errors in the callbacks are not reported to anyone, like in a
+        // browser without a console.
+        let _ = self.execute("__runTimeouts();");
+
+        while self.runtime.is_job_pending() {
+            let _ = self.runtime.execute_pending_job();
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        self.context.with(|context| {
+            context
+                .eval::<(), _>(javascript_code)
+                .catch(&context)
+                .map_err(|exception|
+                    error::evaluation(exception.to_string().trim_end()))
+        })
+    }
+
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        self.context.with(|context| {
+            context
+                .eval::<String, _>(javascript_code)
+                .catch(&context)
+                .map_err(|exception|
+                    error::evaluation(exception.to_string().trim_end()))
+        })
+    }
+}
+
+struct Gui {
+    html_document: String,
+    js_callback: Rc<JavascriptCallback>,
+    engine: Option<Engine>,
+    options: Options,
+}
+
+impl Gui {
+    // Pages are loaded only from 'file://' URLs since there's no network
+    // access.
+    fn document(&self) -> Result<String, Error> {
+        let html_document = match self.options.url {
+            Some(ref url) => {
+                let path = file_path(url).ok_or_else(|| error::loading(
+                    &format!("The headless backend can't load '{}'", url)))?;
+
+                fs::read_to_string(&path).map_err(|error| error::loading(
+                    &format!("Couldn't read '{}': {}", path, error)))?
+            },
+            None => self.html_document.clone(),
+        };
+
+        match self.options.startup_script.is_empty() {
+            true => Ok(html_document),
+            false => Ok(script::inject(
+                &html_document, &self.options.startup_script)),
+        }
+    }
+}
+
+impl PluginGui for Gui {
+    fn size(&self) -> (i32, i32) {
+        match self.engine {
+            Some(_) => self.options.window_size.unwrap_or(DEFAULT_SIZE),
+            None => (0, 0)
+        }
+    }
+
+    fn position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn close(&mut self) {
+        self.engine = None;
+    }
+
+    // The parent handle is ignored so a null pointer can be passed.
+    fn open(&mut self, _parent_handle: *mut c_void) -> Result<(), Error> {
+        let engine = self.document().and_then(|html_document| Engine::new(
+            &html_document,
+            self.js_callback.clone(),
+            self.options.assets.as_deref(),
+            self.options.zoom.unwrap_or(1.0)));
+
+        match engine {
+            Ok(engine) => {
+                self.engine = Some(engine);
+                Ok(())
+            },
+            Err(error) => {
+                if self.options.error_page {
+                    let html_document = format!(
+                        "<pre id=\"error\"></pre><script>\
+                        document.getElementById(\"error\").textContent = {};\
+                        </script>",
+                        javascript_string(&error::report(&error)));
+
+                    self.engine = Engine::new(
+                        &html_document,
+                        self.js_callback.clone(),
+                        None,
+                        self.options.zoom.unwrap_or(1.0)).ok();
+                }
+
+                Err(error)
+            }
+        }
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.engine.is_some()
+    }
+
+    // A new engine is created since the DOM can't be replaced.
+    fn load(&mut self, html_document: String) -> Result<(), Error> {
+        self.html_document = html_document;
+        self.options.url = None;
+
+        match self.engine.take() {
+            Some(_) => self.open(null_mut()),
+            None => Ok(()),
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        self.options.window_size = Some((width, height));
+    }
+
+    // There's nothing to zoom without rendering, so only the page's
+    // 'devicePixelRatio' changes.
+    fn set_zoom(&mut self, zoom: f64) {
+        self.options.zoom = Some(zoom);
+
+        if let Some(ref engine) = self.engine {
+            let _ = engine.set_zoom(zoom);
+        }
+    }
+
+    fn idle(&mut self) {
+        if let Some(ref engine) = self.engine {
+            engine.idle();
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        if let Some(ref engine) = self.engine {
+            engine.execute(javascript_code)
+        } else {
+            Err(Error::WindowClosed)
+        }
+    }
+
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        if let Some(ref engine) = self.engine {
+            engine.evaluate(javascript_code)
+        } else {
+            Err(Error::WindowClosed)
+        }
+    }
+}
+
+pub fn new_plugin_gui(
+    html_document: String,
+    js_callback: JavascriptCallback,
+    options: Options) -> Box<dyn PluginGui>
+{
+    Box::new(
+        Gui {
+            html_document,
+            js_callback: Rc::new(js_callback),
+            engine: None,
+            options,
+        })
+}
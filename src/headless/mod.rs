@@ -0,0 +1,4 @@
+mod document;
+mod gui;
+
+pub use headless::gui::new_plugin_gui;
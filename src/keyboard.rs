@@ -0,0 +1,273 @@
+// Decides which keys the page consumes and which keys reach the host, so
+// that host shortcuts (e.g. the spacebar starting the playback) keep working
+// while the editor has focus.
+//
+// The page captures the keyboard with 'vst.captureKeyboard(true)' and
+// releases it with 'vst.captureKeyboard(false)'. Text fields capture it
+// automatically while they are focused. A captured keyboard sends all keys
+// to the page regardless of the policy.
+//
+// The Windows backend re-posts forwarded keys to the host's window. Other
+// backends deliver keys to the page as is; the policy only determines the
+// answers to 'Editor::key_down' and 'Editor::key_up'.
+
+use std::cell::Cell;
+
+use vst::editor::{Key, KeyCode};
+
+const MESSAGE_PREFIX: &str = "vst-gui:keyboard ";
+
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    (function() {
+        var captured = false;
+        var editing = false;
+
+        function update() {
+            external.invoke(
+                "vst-gui:keyboard " +
+                (captured || editing ? "capture" : "release"));
+        }
+
+        function isEditable(element) {
+            if (!element) {
+                return false;
+            }
+            if (element.isContentEditable) {
+                return true;
+            }
+            if (element.tagName === "TEXTAREA") {
+                return !element.readOnly;
+            }
+            if (element.tagName === "INPUT") {
+                var type = (element.type || "text").toLowerCase();
+                return !element.readOnly && [
+                    "button", "checkbox", "color", "file", "hidden", "image",
+                    "radio", "range", "reset", "submit"
+                ].indexOf(type) === -1;
+            }
+            return false;
+        }
+
+        vst.captureKeyboard = function(capture) {
+            captured = capture !== false;
+            update();
+        };
+
+        document.addEventListener("focusin", function(event) {
+            if (isEditable(event.target)) {
+                editing = true;
+                update();
+            }
+        });
+        document.addEventListener("focusout", function(event) {
+            if (editing) {
+                editing = false;
+                update();
+            }
+        });
+    })();
+"#;
+
+// A key pressed or released while the editor has focus.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyPress {
+    // The character typed with the key, e.g. ' ' for the spacebar.
+    pub character: Option<char>,
+    // The key for keys which don't type characters.
+    pub key: Option<Key>,
+    // A combination of 'KeyPress::SHIFT', 'ALT', 'COMMAND' and 'CONTROL'.
+    pub modifiers: u8,
+}
+
+impl KeyPress {
+    // The values of the VST modifier flags.
+    pub const SHIFT: u8 = 1;
+    pub const ALT: u8 = 2;
+    // The Control key on macOS.
+    pub const COMMAND: u8 = 4;
+    // The Control key on Windows and Linux, the Command key on macOS.
+    pub const CONTROL: u8 = 8;
+}
+
+impl From<KeyCode> for KeyPress {
+    fn from(key_code: KeyCode) -> KeyPress {
+        // Hosts pass either a character or a virtual key.
+        match key_code.character {
+            '\0' => KeyPress {
+                character: None,
+                key: Some(key_code.key),
+                modifiers: key_code.modifier,
+            },
+            character => KeyPress {
+                character: Some(character),
+                key: None,
+                modifiers: key_code.modifier,
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub enum KeyboardPolicy {
+    // The page receives all keys. Host shortcuts don't work while the editor
+    // has focus.
+    #[default]
+    ConsumeAll,
+    // The host receives all keys unless the page captured the keyboard.
+    ForwardAll,
+    // The page receives the keys for which the function returns 'true', the
+    // host receives the other keys unless the page captured the keyboard.
+    Custom(Box<dyn Fn(&KeyPress) -> bool>),
+}
+
+#[derive(Default)]
+pub struct Keyboard {
+    policy: KeyboardPolicy,
+    captured: Cell<bool>,
+}
+
+impl Keyboard {
+    pub fn new(policy: KeyboardPolicy) -> Keyboard {
+        Keyboard {
+            policy,
+            captured: Cell::new(false),
+        }
+    }
+
+    // Returns 'true' if the key goes to the page.
+    pub fn consumes(&self, key: &KeyPress) -> bool {
+        if self.captured.get() {
+            return true;
+        }
+
+        match self.policy {
+            KeyboardPolicy::ConsumeAll => true,
+            KeyboardPolicy::ForwardAll => false,
+            KeyboardPolicy::Custom(ref consumes) => consumes(key),
+        }
+    }
+
+    // A new page starts with the released keyboard.
+    pub fn reset(&self) {
+        self.captured.set(false);
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        match message.strip_prefix(MESSAGE_PREFIX)? {
+            "capture" => self.captured.set(true),
+            "release" => self.captured.set(false),
+            _ => {}
+        }
+
+        Some(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_press(character: char) -> KeyPress {
+        KeyPress {
+            character: Some(character),
+            key: None,
+            modifiers: 0,
+        }
+    }
+
+    #[test]
+    fn policy_decides_where_keys_go() {
+        let space = key_press(' ');
+        let digit = key_press('1');
+
+        assert!(Keyboard::default().consumes(&space));
+        assert!(!Keyboard::new(KeyboardPolicy::ForwardAll).consumes(&space));
+
+        let keyboard = Keyboard::new(KeyboardPolicy::Custom(Box::new(|key|
+            key.character.is_some_and(|character| character.is_ascii_digit()))));
+        assert!(keyboard.consumes(&digit));
+        assert!(!keyboard.consumes(&space));
+    }
+
+    #[test]
+    fn captured_keyboard_sends_all_keys_to_the_page() {
+        let keyboard = Keyboard::new(KeyboardPolicy::ForwardAll);
+        let space = key_press(' ');
+
+        assert_eq!(
+            keyboard.handle("vst-gui:keyboard capture"), Some(String::new()));
+        assert!(keyboard.consumes(&space));
+
+        keyboard.handle("vst-gui:keyboard release");
+        assert!(!keyboard.consumes(&space));
+
+        keyboard.handle("vst-gui:keyboard capture");
+        keyboard.reset();
+        assert!(!keyboard.consumes(&space));
+
+        assert_eq!(keyboard.handle("capture"), None);
+    }
+
+    #[test]
+    fn key_press_has_either_a_character_or_a_key() {
+        let character = KeyPress::from(KeyCode {
+            character: 'a',
+            key: Key::Back,
+            modifier: KeyPress::SHIFT,
+        });
+        assert_eq!(character.character, Some('a'));
+        assert!(character.key.is_none());
+        assert_eq!(character.modifiers, KeyPress::SHIFT);
+
+        let key = KeyPress::from(KeyCode {
+            character: '\0',
+            key: Key::Space,
+            modifier: 0,
+        });
+        assert!(key.character.is_none());
+        assert!(matches!(key.key, Some(Key::Space)));
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn script_captures_the_keyboard_for_text_fields() {
+        use std::ptr::null_mut;
+        use std::rc::Rc;
+
+        use headless;
+        use script;
+
+        let keyboard = Rc::new(Keyboard::new(KeyboardPolicy::ForwardAll));
+        let page_keyboard = keyboard.clone();
+        let space = key_press(' ');
+
+        let mut gui = headless::new_plugin_gui(
+            script::inject("", SCRIPT),
+            Box::new(move |message| {
+                page_keyboard.handle(&message).unwrap_or_default()
+            }),
+            Default::default());
+        gui.open(null_mut()).unwrap();
+
+        let focus = |event_type: &str, element: &str| gui.execute(&format!(
+            "var event = new Event('{}');\
+            event.target = {};\
+            document.dispatchEvent(event);", event_type, element)).unwrap();
+
+        focus("focusin", "{tagName: 'INPUT', type: 'checkbox'}");
+        assert!(!keyboard.consumes(&space));
+
+        focus("focusin", "{tagName: 'INPUT', type: 'text'}");
+        assert!(keyboard.consumes(&space));
+
+        focus("focusout", "{tagName: 'INPUT', type: 'text'}");
+        assert!(!keyboard.consumes(&space));
+
+        gui.execute("vst.captureKeyboard(true);").unwrap();
+        assert!(keyboard.consumes(&space));
+        gui.execute("vst.captureKeyboard(false);").unwrap();
+        assert!(!keyboard.consumes(&space));
+    }
+}
@@ -4,40 +4,321 @@ extern crate memoffset;
 #[cfg(windows)]
 #[macro_use]
 extern crate winapi;
+#[cfg(target_os = "linux")]
+extern crate libloading;
+#[macro_use]
+extern crate log;
+#[cfg(feature = "headless")]
+extern crate rquickjs;
+extern crate serde;
+extern crate serde_json;
 extern crate vst;
+extern crate vst_gui_macros;
 
-use std::error::Error;
+use std::collections::BTreeMap;
 use std::os::raw::c_void;
+#[cfg(feature = "hot-reload")]
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use serde::Serialize;
+use vst::plugin::{HostCallback, PluginParameters};
 
+#[cfg(feature = "headless")]
+mod headless;
+#[cfg(target_os = "linux")]
+mod linux;
 #[cfg(windows)]
 mod win32;
 
+pub mod assets;
+mod console;
+#[cfg(feature = "declarative")]
+pub mod declarative;
+mod deferred;
+mod error;
+mod events;
+mod handle;
+mod handlers;
+mod keyboard;
+mod parameters;
+#[cfg(feature = "hot-reload")]
+mod reload;
+mod resize;
+pub mod rpc;
+mod script;
+mod stream;
+pub mod widgets;
+
 mod lib {
-    use std::error::Error;
     use std::os::raw::c_void;
+    use std::rc::Rc;
+
+    use assets::AssetProvider;
+    use error::Error;
+    use keyboard::Keyboard;
 
     pub type JavascriptCallback = Box<dyn Fn(String) -> String>;
 
+    // Options which are passed to the backends as is.
+    #[derive(Default)]
+    pub struct Options {
+        pub assets: Option<Rc<dyn AssetProvider>>,
+        pub window_size: Option<(i32, i32)>,
+        // Enables the web inspector where it's supported.
+        pub developer_extras: bool,
+        // Keeps the window open with a description of the failure when the
+        // page can't be created.
+        pub error_page: bool,
+        // The names of the handlers called with 'external.<name>(message)'.
+        // Their calls are passed to the callback as special messages.
+        pub handlers: Vec<String>,
+        // The URL which is loaded instead of the document.
+        pub url: Option<String>,
+        // The crate's scripts which run in every page when 'url' is set.
+        // Backends run them at the start of the page where it's possible.
+        pub startup_script: String,
+        // The page zoom set with 'PluginGui::set_zoom'. 'None' stands for 1.
+        pub zoom: Option<f64>,
+        // Decides which keys are forwarded to the host.
+        pub keyboard: Rc<Keyboard>,
+    }
+
     pub trait PluginGui {
         fn size(&self) -> (i32, i32);
         fn position(&self) -> (i32, i32);
         fn close(&mut self);
-        fn open(&mut self, parent_handle: *mut c_void) -> bool;
+        fn open(&mut self, parent_handle: *mut c_void) -> Result<(), Error>;
         fn is_open(&mut self) -> bool;
-        fn execute(&self, javascript_code: &str) -> Result<(), Box<dyn Error>>;
+        // Replaces the document. The open window loads it in place.
+        fn load(&mut self, html_document: String) -> Result<(), Error>;
+        // Resizes the window if it's open. The size is used when the window
+        // is opened next time in any case.
+        fn resize(&mut self, width: i32, height: i32);
+        // Scales the page content relative to the system scale factor. The
+        // zoom is kept when the window is opened next time.
+        fn set_zoom(&mut self, zoom: f64);
+        // The scale factor which the browser applies to pages by itself.
+        fn system_scale_factor(&self) -> f64 {
+            1.0
+        }
+        fn idle(&mut self) {}
+        fn execute(&self, javascript_code: &str) -> Result<(), Error>;
+        // Returns the result of the code converted to a string by the page.
+        fn evaluate(&self, javascript_code: &str) -> Result<String, Error>;
     }
 }
 
 pub struct PluginGui {
     gui: Box<dyn lib::PluginGui>,
+    events: Rc<events::Events>,
+    parameters: Option<Rc<parameters::Binding>>,
+    queue: handle::Queue,
+    streams: Vec<stream::Stream>,
+    resizer: Rc<resize::Resizer>,
+    keyboard: Rc<keyboard::Keyboard>,
+    hooks: Hooks,
+    template: script::Template,
+    #[cfg(feature = "hot-reload")]
+    watcher: Option<reload::Watcher>,
+    last_error: Option<Error>,
+}
+
+// Called with the GUI when the page's lifecycle changes (see
+// 'PluginGuiBuilder::on_ready').
+pub type LifecycleHook = Box<dyn Fn(&PluginGui)>;
+
+#[derive(Default)]
+struct Hooks {
+    ready: Option<LifecycleHook>,
+    close: Option<LifecycleHook>,
+    reload: Option<LifecycleHook>,
+}
+
+impl Hooks {
+    fn call(hook: &Option<LifecycleHook>, gui: &PluginGui) {
+        if let Some(ref hook) = *hook {
+            hook(gui);
+        }
+    }
 }
 
 impl PluginGui {
     // Calls the Javascript 'eval' function with the specified argument.
-    // This method always returns an error when the plugin window is closed.
-    pub fn execute(&self, javascript_code: &str) -> Result<(), Box<dyn Error>> {
+    // Scripts passed while the page is loading are queued and executed in
+    // order once its DOM is loaded (see the 'events' module for pages which
+    // don't report it); 'Ok' is returned for them right away.
+    // This method always returns 'Error::WindowClosed' when the plugin
+    // window is closed.
+    pub fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        if self.events.is_loading() {
+            self.events.push_script(&*self.gui, javascript_code.to_string());
+            return Ok(());
+        }
+
+        // Scripts queued while the page was loading run first.
+        self.events.flush(&*self.gui);
         self.gui.execute(javascript_code)
     }
+
+    // Returns 'true' if the page's DOM is loaded and scripts are executed
+    // right away.
+    pub fn is_ready(&self) -> bool {
+        self.events.is_ready()
+    }
+
+    // Replaces the document keeping the callbacks and the crate's scripts.
+    // The open window loads the new document in place: the 'on_reload' and
+    // 'on_ready' hooks are called like when the page reloads itself.
+    pub fn set_html(&mut self, html_document: &str) -> Result<(), Error> {
+        self.events.reload();
+        self.gui.load(self.template.apply(html_document))
+    }
+
+    // Reloads the document from the development directory if it's changed.
+    #[cfg(feature = "hot-reload")]
+    fn reload_changed_document(&mut self) {
+        let html_document = match self.watcher {
+            Some(ref mut watcher) => match watcher.poll() {
+                true => reload::read_document(watcher.directory()),
+                false => return,
+            },
+            None => return,
+        };
+
+        let result = html_document
+            .and_then(|html_document| self.set_html(&html_document));
+
+        if let Err(error) = result {
+            warn!("Couldn't reload the page: {}", error);
+        }
+    }
+
+    // Evaluates the expression in the page's global scope and returns its
+    // value converted to JSON. 'undefined' and values which JSON can't
+    // represent (e.g. functions) are returned as 'null'. Exceptions are
+    // returned as 'Error::ScriptEvaluation' with their messages. On Linux
+    // the call processes pending GTK events while it waits for the result,
+    // so callbacks may be called from within it.
+    pub fn evaluate(
+        &self, expression: &str) -> Result<serde_json::Value, Error>
+    {
+        let javascript_code = format!(
+            "(function(value) {{\
+                var result = JSON.stringify(value);\
+                return result === undefined ? \"null\" : result;\
+            }})((0, eval)({}));",
+            events::to_javascript(expression)?);
+
+        let result = self.gui.evaluate(&javascript_code)?;
+
+        Ok(serde_json::from_str(&result)?)
+    }
+
+    // Resizes the window. The size is logical (see 'scale_factor') and is
+    // adjusted to the constraints set with 'PluginGuiBuilder::min_size',
+    // 'max_size' and 'aspect_ratio'. The host (if any) is asked to resize its
+    // window first. The page receives the 'resize' event with the
+    // '{width, height}' payload. Returns the adjusted size or
+    // 'Error::ResizeRejected' if the host refused to resize its window (see
+    // 'PluginGuiBuilder::strict_host_resize'). The window keeps its size in
+    // that case.
+    pub fn resize(
+        &mut self, width: i32, height: i32) -> Result<(i32, i32), Error>
+    {
+        let size = self.resizer.constraints().apply((width, height));
+        let physical_size = self.resizer.to_physical(size);
+
+        if !self.resizer.notify_host(physical_size) {
+            return Err(Error::ResizeRejected);
+        }
+
+        self.set_size(size, physical_size)?;
+
+        Ok(size)
+    }
+
+    // Resizes the window to the size which the host accepted.
+    fn set_size(
+        &mut self,
+        size: (i32, i32),
+        physical_size: (i32, i32)) -> Result<(), Error>
+    {
+        self.resizer.set_size(size);
+        self.gui.resize(physical_size.0, physical_size.1);
+
+        let payload: BTreeMap<&str, i32> =
+            vec![("width", size.0), ("height", size.1)].into_iter().collect();
+        self.events.emit(&*self.gui, "resize", &payload)
+    }
+
+    // The number of physical pixels per logical (CSS) pixel. Sizes passed to
+    // 'PluginGuiBuilder' and 'resize' are logical while 'Editor::size'
+    // reports physical sizes to the host. By default it's the system scale
+    // factor.
+    pub fn scale_factor(&self) -> f64 {
+        self.resizer.scale_factor()
+    }
+
+    // Zooms the page and resizes the window keeping its logical size, e.g.
+    // when the host reports that the editor moved to another screen. The
+    // page receives the 'scale' event with the new factor. Returns
+    // 'Error::ResizeRejected' if the host refused to resize its window (see
+    // 'resize'). The page keeps its zoom in that case.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) -> Result<(), Error> {
+        resize::validate_scale_factor(scale_factor)?;
+
+        match self.resizer.size(&*self.gui) {
+            Some(size) => {
+                let size = self.resizer.constraints().apply(size);
+                let physical_size = resize::to_physical(size, scale_factor);
+
+                if !self.resizer.notify_host(physical_size) {
+                    return Err(Error::ResizeRejected);
+                }
+
+                self.resizer.set_scale_factor(&mut *self.gui, scale_factor);
+                self.set_size(size, physical_size)?;
+            },
+            None => self.resizer.set_scale_factor(&mut *self.gui, scale_factor),
+        }
+
+        self.events.emit(&*self.gui, "scale", &scale_factor)
+    }
+
+    // Passes the serialized payload to the handlers registered in the page
+    // with 'vst.on(event_name, function(payload) {...})'. Events are queued
+    // while the window is closed or the page is loading and are delivered in
+    // order once the page is loaded. An error is returned only when the
+    // payload can't be serialized.
+    pub fn emit<T: Serialize + ?Sized>(
+        &self, event_name: &str, payload: &T) -> Result<(), Error>
+    {
+        self.events.emit(&*self.gui, event_name, payload)
+    }
+
+    // Returns a handle which can be used to update the GUI from other
+    // threads. The updates are applied when the host calls 'Editor::idle'.
+    pub fn handle(&self) -> GuiHandle {
+        self.queue.handle()
+    }
+
+    // Passes samples written to the stream to the handlers registered in the
+    // page with 'vst.onStream(name, function(samples) {...})', where
+    // 'samples' is a 'Float32Array'. New samples are collected on idle at
+    // most 'frame_rate' times per second.
+    pub fn add_stream(
+        &mut self, name: &str, reader: StreamReader, frame_rate: f32)
+    {
+        self.streams.push(stream::Stream::new(name, reader, frame_rate));
+    }
+
+    // Returns the reason of the last failed 'Editor::open' call. The error
+    // is cleared when the window is opened successfully.
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
 }
 
 impl vst::editor::Editor for PluginGui {
@@ -49,30 +330,864 @@ impl vst::editor::Editor for PluginGui {
         self.gui.position()
     }
 
+    fn idle(&mut self) {
+        self.gui.idle();
+
+        #[cfg(feature = "hot-reload")]
+        self.reload_changed_document();
+
+        if let Some((width, height)) = self.resizer.take_request() {
+            if let Err(error) = self.resize(width, height) {
+                warn!("The page couldn't resize the window: {}", error);
+            }
+        }
+
+        if self.events.take_unloaded() {
+            self.keyboard.reset();
+
+            if let Some(ref parameters) = self.parameters {
+                parameters.reset();
+            }
+
+            Hooks::call(&self.hooks.reload, self);
+        }
+
+        self.events.check_loading(&*self.gui);
+        self.queue.drain(&*self.gui, &self.events);
+        self.events.flush(&*self.gui);
+        self.queue.count_dropped(&self.events);
+
+        if self.events.take_loaded() {
+            Hooks::call(&self.hooks.ready, self);
+        }
+
+        for stream in &mut self.streams {
+            stream.update(&*self.gui, self.events.is_ready());
+        }
+
+        if let Some(ref parameters) = self.parameters {
+            if self.events.is_ready() {
+                parameters.synchronize(&*self.gui);
+            }
+        }
+    }
+
     fn close(&mut self) {
+        Hooks::call(&self.hooks.close, self);
+
+        self.events.close();
+        self.keyboard.reset();
         self.gui.close()
     }
 
     fn open(&mut self, parent_handle: *mut c_void) -> bool {
-        self.gui.open(parent_handle)
+        self.events.open();
+        self.keyboard.reset();
+
+        if let Some(ref parameters) = self.parameters {
+            parameters.reset();
+        }
+
+        match self.gui.open(parent_handle) {
+            Ok(()) => {
+                self.last_error = None;
+                true
+            },
+            Err(error) => {
+                self.events.close();
+                self.last_error = Some(error);
+                // The window stays open when it displays the error page.
+                self.gui.is_open()
+            }
+        }
     }
 
     fn is_open(&mut self) -> bool {
         self.gui.is_open()
     }
+
+    // Returns 'false' for keys which the host should handle itself.
+    fn key_up(&mut self, key_code: vst::editor::KeyCode) -> bool {
+        self.keyboard.consumes(&key_code.into())
+    }
+
+    fn key_down(&mut self, key_code: vst::editor::KeyCode) -> bool {
+        self.keyboard.consumes(&key_code.into())
+    }
 }
 
+pub use assets::{Asset, AssetProvider, Assets};
+pub use console::{ConsoleLevel, ConsoleMessage, ConsoleSink};
+pub use deferred::{AsyncCallback, Responder};
+pub use error::Error;
+pub use handle::{GuiHandle, HandleStatistics};
+pub use keyboard::{KeyPress, KeyboardPolicy};
 pub use lib::JavascriptCallback;
+pub use stream::{new_stream, StreamReader, StreamWriter};
+pub use vst_gui_macros::include_assets;
 
-pub fn new_plugin_gui(
+type Backend =
+    fn(String, JavascriptCallback, lib::Options) -> Box<dyn lib::PluginGui>;
+
+fn new_native_gui(
     html_document: String,
     js_callback: JavascriptCallback,
-    window_size: Option<(i32, i32)>) -> PluginGui
+    options: lib::Options) -> Box<dyn lib::PluginGui>
 {
     #[cfg(windows)]
     {
-        PluginGui {
-            gui: win32::new_plugin_gui(html_document, js_callback, window_size)
+        win32::new_plugin_gui(html_document, js_callback, options)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        linux::new_plugin_gui(html_document, js_callback, options)
+    }
+}
+
+// Configures and creates a 'PluginGui':
+//
+//     let gui = PluginGuiBuilder::new()
+//         .html(HTML)
+//         .callback(js_callback)
+//         .parameters(parameters, PARAMETER_COUNT)
+//         .size(640, 480)
+//         .build()?;
+//
+// Only the document is required.
+pub struct PluginGuiBuilder {
+    backend: Backend,
+    html_document: Option<String>,
+    js_callback: Option<JavascriptCallback>,
+    async_callback: Option<AsyncCallback>,
+    handlers: Vec<(String, JavascriptCallback)>,
+    hooks: Hooks,
+    #[cfg(feature = "hot-reload")]
+    development_directory: Option<PathBuf>,
+    parameters: Option<(Arc<dyn PluginParameters>, i32)>,
+    host: Option<HostCallback>,
+    strict_host_resize: bool,
+    constraints: resize::SizeConstraints,
+    scale_factor: Option<f64>,
+    scripts: Vec<String>,
+    console: Option<ConsoleSink>,
+    options: lib::Options,
+}
+
+impl Default for PluginGuiBuilder {
+    fn default() -> PluginGuiBuilder {
+        PluginGuiBuilder::new()
+    }
+}
+
+impl PluginGuiBuilder {
+    pub fn new() -> PluginGuiBuilder {
+        PluginGuiBuilder {
+            backend: new_native_gui,
+            html_document: None,
+            js_callback: None,
+            async_callback: None,
+            handlers: Vec::new(),
+            hooks: Hooks::default(),
+            #[cfg(feature = "hot-reload")]
+            development_directory: None,
+            parameters: None,
+            host: None,
+            strict_host_resize: false,
+            constraints: resize::SizeConstraints::default(),
+            scale_factor: None,
+            scripts: Vec::new(),
+            console: None,
+            options: lib::Options::default(),
+        }
+    }
+
+    pub fn html<S: Into<String>>(mut self, html_document: S) -> Self {
+        self.html_document = Some(html_document.into());
+        self
+    }
+
+    // Loads the page from the URL instead of the document, e.g. from a local
+    // development server like 'http://127.0.0.1:5173/' or from a 'file://'
+    // URL. The page can use 'external.invoke' and the crate's scripts as
+    // usual. On Linux this requires WebKitGTK 2.36 or newer. On Windows the
+    // crate's scripts run once the page's DOM is parsed, so scripts of the
+    // page should use the 'vst' object after the 'DOMContentLoaded' event.
+    // The headless backend loads only 'file://' URLs.
+    pub fn url<S: Into<String>>(mut self, url: S) -> Self {
+        self.options.url = Some(url.into());
+        self
+    }
+
+    // Handles the 'external.invoke' calls. By default the calls return empty
+    // strings.
+    pub fn callback(mut self, js_callback: JavascriptCallback) -> Self {
+        self.js_callback = Some(js_callback);
+        self
+    }
+
+    // Handles the 'external.invokeAsync' calls which return promises. The
+    // callback receives the message and a 'Responder' which settles the
+    // promise. The responder can be completed later from any thread: the
+    // response reaches the page when the host calls 'Editor::idle'. Internet
+    // Explorer, which the Windows backend uses, has no 'Promise', so pages
+    // have to include a polyfill to make the calls there.
+    pub fn async_callback<F>(mut self, callback: F) -> Self
+        where F: Fn(String, Responder) + 'static
+    {
+        self.async_callback = Some(Box::new(callback));
+        self
+    }
+
+    // Handles the 'external.<name>(message)' calls so that separate parts of
+    // the plugin can have their own endpoints. The name must be a Javascript
+    // identifier other than 'invoke' and 'invokeAsync'.
+    pub fn handler(mut self, name: &str, handler: JavascriptCallback) -> Self {
+        self.handlers.push((name.to_string(), handler));
+        self
+    }
+
+    // Called on 'Editor::idle' each time the page's DOM is loaded, after the
+    // scripts and events queued while the page was loading.
+    pub fn on_ready<F>(mut self, hook: F) -> Self
+        where F: Fn(&PluginGui) + 'static
+    {
+        self.hooks.ready = Some(Box::new(hook));
+        self
+    }
+
+    // Called when the host closes the window. The page still runs scripts
+    // passed to 'PluginGui::execute' at this point.
+    pub fn on_close<F>(mut self, hook: F) -> Self
+        where F: Fn(&PluginGui) + 'static
+    {
+        self.hooks.close = Some(Box::new(hook));
+        self
+    }
+
+    // Called on 'Editor::idle' when the page is unloaded while the window
+    // stays open (e.g. after 'location.reload()'). Scripts are queued until
+    // the new page is ready; 'on_ready' is called then.
+    pub fn on_reload<F>(mut self, hook: F) -> Self
+        where F: Fn(&PluginGui) + 'static
+    {
+        self.hooks.reload = Some(Box::new(hook));
+        self
+    }
+
+    // Serves the assets to the page from the 'plugin://app/' URL. The
+    // document's base URL is 'plugin://app/' so relative URLs refer to the
+    // assets as well. On Windows each window has its own host instead of
+    // 'app', so the assets should be referred to with relative URLs.
+    pub fn assets<A: AssetProvider + 'static>(mut self, assets: A) -> Self {
+        self.options.assets = Some(Rc::new(assets));
+        self
+    }
+
+    // Loads the document from 'index.html' in the directory and serves the
+    // other files of the directory as assets instead of the ones set with
+    // 'html' and 'assets'. The page is reloaded in place when the files
+    // change. Requires the 'hot-reload' feature which is meant for
+    // development builds only.
+    #[cfg(feature = "hot-reload")]
+    pub fn development_directory<P: Into<PathBuf>>(mut self, directory: P)
+        -> Self
+    {
+        self.development_directory = Some(directory.into());
+        self
+    }
+
+    // Decides which keys the page receives and which keys are forwarded to
+    // the host while the editor has focus. By default the page receives all
+    // keys. 'KeyboardPolicy::ForwardAll' lets the host receive the keys unless
+    // the page called 'vst.captureKeyboard(true)' or a text field has focus.
+    pub fn keyboard(mut self, policy: KeyboardPolicy) -> Self {
+        self.options.keyboard = Rc::new(keyboard::Keyboard::new(policy));
+        self
+    }
+
+    // Exposes the first 'parameter_count' parameters to the page through the
+    // 'vst.parameters' object. Messages sent by this object are handled
+    // internally and are not passed to the callback. Parameter changes made
+    // by the host are passed to the page when the host calls 'Editor::idle'.
+    pub fn parameters(
+        mut self,
+        parameters: Arc<dyn PluginParameters>,
+        parameter_count: i32) -> Self
+    {
+        self.parameters = Some((parameters, parameter_count));
+        self
+    }
+
+    // Generates the document from the description and exposes the
+    // parameters its controls are bound to. The description's size is used
+    // as the window size if it's set.
+    #[cfg(feature = "declarative")]
+    pub fn description(
+        self,
+        description: &declarative::Description,
+        parameters: Arc<dyn PluginParameters>) -> Self
+    {
+        let builder = self
+            .html(description.html())
+            .parameters(parameters, description.parameter_count());
+
+        match description.size {
+            Some((width, height)) => builder.size(width, height),
+            None => builder,
         }
     }
+
+    // Passes the 'vst.beginEdit', 'vst.setParameter' and 'vst.endEdit' calls
+    // to the host so that it can record automation and undo parameter
+    // changes. The host is also asked to resize its window when the editor
+    // is resized.
+    pub fn host(mut self, host: HostCallback) -> Self {
+        self.host = Some(host);
+        self
+    }
+
+    // Hosts answer the 'sizeWindow' request with 1 when they resize their
+    // window, but many hosts answer 0 even then. By default the answer is
+    // ignored and the editor is resized regardless. When this option is set,
+    // 0 counts as a refusal: 'PluginGui::resize' returns
+    // 'Error::ResizeRejected' and the window keeps its size.
+    pub fn strict_host_resize(mut self, strict: bool) -> Self {
+        self.strict_host_resize = strict;
+        self
+    }
+
+    // Adds the custom elements of the 'widgets' module to the document.
+    pub fn widgets(self) -> Self {
+        self.script(&widgets::inline_script())
+    }
+
+    // Adds a script which runs before any scripts of the document but after
+    // the crate's scripts, so it can use the 'vst' object. Scripts run in the
+    // order they are added.
+    pub fn script(mut self, script: &str) -> Self {
+        self.scripts.push(script.to_string());
+        self
+    }
+
+    // Receives the page's console output and uncaught errors. By default
+    // they are passed to the 'log' crate with the 'vst_gui::console' target.
+    pub fn console<F>(mut self, sink: F) -> Self
+        where F: Fn(&ConsoleMessage) + 'static
+    {
+        self.console = Some(Box::new(sink));
+        self
+    }
+
+    // The logical window size (see 'scale_factor'). By default the window
+    // takes a quarter of the screen.
+    pub fn size(mut self, width: i32, height: i32) -> Self {
+        self.options.window_size = Some((width, height));
+        self
+    }
+
+    // The limits of the window size applied when the window is resized with
+    // 'PluginGui::resize' or 'vst.resize(width, height)'. The initial size
+    // set with 'size' is adjusted to the constraints as well.
+    pub fn min_size(mut self, width: i32, height: i32) -> Self {
+        self.constraints.min_size = Some((width, height));
+        self
+    }
+
+    pub fn max_size(mut self, width: i32, height: i32) -> Self {
+        self.constraints.max_size = Some((width, height));
+        self
+    }
+
+    // The initial number of physical pixels per logical (CSS) pixel, e.g.
+    // the one provided by the host. By default it's the system scale factor.
+    pub fn scale_factor(mut self, scale_factor: f64) -> Self {
+        self.scale_factor = Some(scale_factor);
+        self
+    }
+
+    // Keeps the width divided by the height equal to the ratio when the
+    // window is resized. The height is adjusted to the width.
+    pub fn aspect_ratio(mut self, ratio: f64) -> Self {
+        self.constraints.aspect_ratio = Some(ratio);
+        self
+    }
+
+    // Enables the web inspector (available from the context menu) on Linux.
+    // Other platforms ignore this option.
+    pub fn developer_extras(mut self, enabled: bool) -> Self {
+        self.options.developer_extras = enabled;
+        self
+    }
+
+    // When the editor fails to open (e.g. the browser component is missing)
+    // the window still opens and shows what went wrong along with a
+    // diagnostic string which can be copied into a bug report. The error is
+    // available from 'PluginGui::last_error' either way. Disabled by default.
+    pub fn error_page(mut self, enabled: bool) -> Self {
+        self.options.error_page = enabled;
+        self
+    }
+
+    // Runs the document's scripts in an embedded Javascript engine without
+    // creating any windows. The document is not rendered: only elements with
+    // IDs are available to the scripts and these elements support nothing
+    // but attributes and events. This is intended for automated tests.
+    #[cfg(feature = "headless")]
+    pub fn headless(mut self) -> Self {
+        self.backend = headless::new_plugin_gui;
+        self
+    }
+
+    pub fn build(self) -> Result<PluginGui, Error> {
+        if let Some((width, height)) = self.options.window_size {
+            if width <= 0 || height <= 0 {
+                return Err(error::configuration(
+                    "The window size must be positive"));
+            }
+        }
+
+        self.build_any_size()
+    }
+
+    // Sizes which are not positive are clamped to one pixel.
+    fn build_any_size(mut self) -> Result<PluginGui, Error> {
+        #[cfg(feature = "hot-reload")]
+        let watcher = match self.development_directory.take() {
+            Some(directory) => {
+                self.html_document = Some(reload::read_document(&directory)?);
+                self.options.assets =
+                    Some(Rc::new(reload::DirectoryAssets::new(&directory)));
+                Some(reload::Watcher::new(directory))
+            },
+            None => None,
+        };
+
+        let html_document = match (self.html_document, &self.options.url) {
+            (Some(html_document), None) => html_document,
+            (None, Some(_)) => String::new(),
+            (Some(_), Some(_)) => return Err(error::configuration(
+                "Either the HTML document or the URL must be specified")),
+            (None, None) => return Err(error::configuration(
+                "The HTML document is not specified")),
+        };
+
+        self.constraints.validate()?;
+
+        if let Some(scale_factor) = self.scale_factor {
+            resize::validate_scale_factor(scale_factor)?;
+        }
+
+        let parameters = match self.parameters {
+            Some((_, parameter_count)) if parameter_count < 0 =>
+                return Err(error::configuration(
+                    "The parameter count can't be negative")),
+            Some((parameters, parameter_count)) => Some(
+                parameters::Binding::new(
+                    parameters, parameter_count, self.host)),
+            None => None,
+        };
+
+        let resizer = resize::Resizer::new(
+            self.constraints,
+            self.host,
+            self.strict_host_resize,
+            self.options.window_size.take(),
+            self.scale_factor);
+
+        let handlers = handlers::Handlers::new(self.handlers)?;
+
+        let js_callback = self.js_callback
+            .unwrap_or_else(|| Box::new(|_| String::new()));
+
+        let mut options = self.options;
+        options.handlers = handlers.names();
+
+        let callbacks = Callbacks {
+            js_callback,
+            async_callback: self.async_callback,
+            handlers,
+            console: console::Console::new(self.console),
+            hooks: self.hooks,
+        };
+
+        let gui = new_gui(
+            self.backend,
+            html_document,
+            callbacks,
+            parameters,
+            resizer,
+            &self.scripts,
+            options);
+
+        #[cfg(feature = "hot-reload")]
+        let gui = PluginGui { watcher, ..gui };
+
+        Ok(gui)
+    }
+}
+
+// The plugin's callbacks: the handlers of the messages sent by the page which
+// are not handled by the crate's objects and the lifecycle hooks.
+struct Callbacks {
+    js_callback: JavascriptCallback,
+    async_callback: Option<AsyncCallback>,
+    handlers: handlers::Handlers,
+    console: console::Console,
+    hooks: Hooks,
+}
+
+// Adds the crate's scripts to the document and routes the messages sent by
+// these scripts to the corresponding objects instead of 'js_callback'.
+fn new_gui(
+    backend: Backend,
+    html_document: String,
+    callbacks: Callbacks,
+    parameters: Option<parameters::Binding>,
+    resizer: resize::Resizer,
+    scripts: &[String],
+    mut options: lib::Options) -> PluginGui
+{
+    let events = Rc::new(events::Events::new());
+    let parameters = parameters.map(Rc::new);
+    let resizer = Rc::new(resizer);
+    let keyboard = options.keyboard.clone();
+    let queue = handle::Queue::new();
+    let Callbacks { js_callback, async_callback, handlers, console, hooks } =
+        callbacks;
+    let requests = async_callback.map(|callback|
+        deferred::Requests::new(callback, queue.handle()));
+
+    // The console script runs first to report errors of the other scripts.
+    // User scripts run last.
+    let mut template = script::Template::new();
+
+    template.push(console::SCRIPT);
+
+    if !options.handlers.is_empty() {
+        template.push(handlers::script(&options.handlers));
+    }
+
+    if requests.is_some() {
+        template.push(deferred::SCRIPT);
+    }
+
+    if parameters.is_some() {
+        template.push(parameters::SCRIPT);
+    }
+
+    template.push(keyboard::SCRIPT);
+    template.push(resize::SCRIPT);
+    template.push(stream::SCRIPT);
+    template.push(events::SCRIPT);
+
+    for script in scripts {
+        template.push(script.as_str());
+    }
+
+    // Pages loaded from URLs run the scripts themselves. So do documents
+    // loaded in place of such pages.
+    if options.url.is_some() {
+        options.startup_script = template.script();
+        template = script::Template::new();
+    }
+
+    let js_callback: JavascriptCallback = {
+        let events = events.clone();
+        let parameters = parameters.clone();
+        let resizer = resizer.clone();
+        let keyboard = keyboard.clone();
+
+        Box::new(move |message: String| {
+            if let Some(result) = console.handle(&message) {
+                return result;
+            }
+
+            if let Some(result) = events.handle(&message) {
+                return result;
+            }
+
+            let result = parameters
+                .as_ref()
+                .and_then(|parameters| parameters.handle(&message))
+                .or_else(|| resizer.handle(&message))
+                .or_else(|| keyboard.handle(&message))
+                .or_else(|| handlers.handle(&message))
+                .or_else(|| requests
+                    .as_ref()
+                    .and_then(|requests| requests.handle(&message)));
+
+            match result {
+                Some(result) => result,
+                None => js_callback(message),
+            }
+        })
+    };
+
+    let mut gui = backend(
+        template.apply(&html_document), js_callback, options);
+    resizer.initialize(&mut *gui);
+
+    PluginGui {
+        gui,
+        events,
+        parameters,
+        queue,
+        streams: Vec::new(),
+        resizer,
+        keyboard,
+        hooks,
+        template,
+        #[cfg(feature = "hot-reload")]
+        watcher: None,
+        last_error: None,
+    }
+}
+
+#[deprecated(note = "Use 'PluginGuiBuilder' instead")]
+pub fn new_plugin_gui(
+    html_document: String,
+    js_callback: JavascriptCallback,
+    window_size: Option<(i32, i32)>) -> PluginGui
+{
+    let builder = PluginGuiBuilder::new()
+        .html(html_document)
+        .callback(js_callback);
+
+    let builder = match window_size {
+        Some((width, height)) => builder.size(width, height),
+        None => builder,
+    };
+
+    // This function has never reported configuration errors. The window
+    // size is the only setting which could be invalid here.
+    builder
+        .build_any_size()
+        .expect("A document without other settings is always valid")
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use std::cell::RefCell;
+    use std::ptr::null_mut;
+    use std::thread;
+    use std::time::Duration;
+
+    use serde_json::json;
+    use vst::api::{AEffect, HostCallbackProc};
+    use vst::editor::Editor;
+
+    use super::*;
+
+    const HTML: &str = r#"
+        <button id="play">Play</button>
+        <script>
+            var log = [];
+
+            function record(entry) {
+                log.push(entry);
+            }
+
+            ["first", "second", "third"].forEach(function(name) {
+                vst.on(name, function(payload) {
+                    record(name + " " + payload);
+                });
+            });
+
+            document.getElementById("play").addEventListener(
+                "click", function() {
+                    record("clicked: " + external.invoke("play"));
+                });
+        </script>
+    "#;
+
+    fn open_gui() -> (PluginGui, Rc<RefCell<Vec<String>>>) {
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let callback_messages = messages.clone();
+
+        let gui = PluginGuiBuilder::new()
+            .html(HTML)
+            .callback(Box::new(move |message| {
+                callback_messages.borrow_mut().push(message);
+                "playing".to_string()
+            }))
+            .headless()
+            .build()
+            .unwrap();
+
+        (gui, messages)
+    }
+
+    #[test]
+    fn click_calls_the_callback() {
+        let (mut gui, messages) = open_gui();
+
+        assert!(gui.open(null_mut()));
+        gui.idle();
+        gui.execute(r#"document.getElementById("play").click();"#).unwrap();
+
+        assert_eq!(*messages.borrow(), vec!["play"]);
+        assert_eq!(gui.evaluate("log").unwrap(), json!(["clicked: playing"]));
+    }
+
+    #[test]
+    fn exceptions_thrown_while_loading_are_reported() {
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let console_messages = messages.clone();
+
+        let mut gui = PluginGuiBuilder::new()
+            .html(
+                "<script>\
+                    var log = [];\
+                    document.addEventListener('DOMContentLoaded', function() {\
+                        log.push('loaded');\
+                        throw 'listener';\
+                    });\
+                    window.addEventListener('load', function() {\
+                        log.push('load');\
+                    });\
+                    throw new Error('script');\
+                </script>\
+                <script>log.push('second');</script>")
+            .console(move |message: &ConsoleMessage| {
+                console_messages.borrow_mut().push(
+                    (message.level, message.message.clone()));
+            })
+            .headless()
+            .build()
+            .unwrap();
+
+        assert!(gui.open(null_mut()));
+        gui.idle();
+
+        assert!(gui.is_ready());
+        assert_eq!(
+            gui.evaluate("log").unwrap(), json!(["second", "loaded", "load"]));
+        assert_eq!(
+            *messages.borrow(),
+            vec![
+                (ConsoleLevel::Uncaught, "Error: script".to_string()),
+                (ConsoleLevel::Uncaught, "listener".to_string()),
+            ]);
+    }
+
+    #[test]
+    fn scripts_reach_pages_which_do_not_report_loading() {
+        // The page's script removes the listener which reports the DOM like
+        // a browser without 'DOMContentLoaded' would.
+        let mut gui = PluginGuiBuilder::new()
+            .html(
+                "<script>\
+                    var log = [];\
+                    document.listeners.DOMContentLoaded = [];\
+                </script>")
+            .headless()
+            .build()
+            .unwrap();
+
+        assert!(gui.open(null_mut()));
+        gui.execute("log.push('script');").unwrap();
+        gui.idle();
+        assert!(!gui.is_ready());
+
+        thread::sleep(Duration::from_millis(1100));
+        gui.idle();
+
+        assert!(gui.is_ready());
+        assert_eq!(gui.evaluate("log").unwrap(), json!(["script"]));
+    }
+
+    #[test]
+    fn events_emitted_before_open_are_delivered_in_order() {
+        let (mut gui, _) = open_gui();
+
+        gui.emit("first", &1).unwrap();
+        gui.emit("second", &2).unwrap();
+
+        // Scripts are not queued while the window is closed.
+        assert_eq!(gui.execute("record('lost')"), Err(Error::WindowClosed));
+
+        assert!(gui.open(null_mut()));
+        gui.execute("record('script')").unwrap();
+        gui.emit("third", &3).unwrap();
+
+        assert_eq!(
+            gui.evaluate("log").unwrap(),
+            json!(["first 1", "second 2", "script", "third 3"]));
+    }
+
+    #[test]
+    fn scripts_passed_while_loading_are_delivered_in_order() {
+        let (mut gui, _) = open_gui();
+
+        assert!(gui.open(null_mut()));
+        gui.idle();
+
+        // The page starts unloading, so the next document receives the
+        // queued events and scripts.
+        gui.execute("window.dispatchEvent(new Event('pagehide'));").unwrap();
+        assert!(!gui.is_ready());
+
+        gui.emit("first", &1).unwrap();
+        gui.execute("record('script')").unwrap();
+        gui.emit("second", &2).unwrap();
+
+        gui.set_html(HTML).unwrap();
+        gui.idle();
+
+        assert_eq!(
+            gui.evaluate("log").unwrap(),
+            json!(["first 1", "script", "second 2"]));
+    }
+
+    fn open_scaled_gui(answer: HostCallbackProc) -> PluginGui {
+        let mut gui = PluginGuiBuilder::new()
+            .html("")
+            .host(HostCallback::wrap(answer, null_mut()))
+            .strict_host_resize(true)
+            .size(400, 300)
+            .scale_factor(1.0)
+            .headless()
+            .build()
+            .unwrap();
+
+        assert!(gui.open(null_mut()));
+        gui
+    }
+
+    fn accepting_host(
+        _: *mut AEffect, _: i32, _: i32, _: isize, _: *mut c_void, _: f32)
+        -> isize
+    {
+        1
+    }
+
+    fn refusing_host(
+        _: *mut AEffect, _: i32, _: i32, _: isize, _: *mut c_void, _: f32)
+        -> isize
+    {
+        0
+    }
+
+    #[test]
+    fn scale_factor_changes_when_the_host_resizes_its_window() {
+        let mut gui = open_scaled_gui(accepting_host);
+
+        gui.set_scale_factor(2.0).unwrap();
+
+        assert_eq!(gui.scale_factor(), 2.0);
+        assert_eq!(Editor::size(&gui), (800, 600));
+        assert_eq!(gui.evaluate("devicePixelRatio").unwrap(), json!(2));
+    }
+
+    #[test]
+    fn rejected_scale_factor_keeps_the_zoom() {
+        let mut gui = open_scaled_gui(refusing_host);
+
+        assert_eq!(gui.set_scale_factor(2.0), Err(Error::ResizeRejected));
+
+        assert_eq!(gui.scale_factor(), 1.0);
+        assert_eq!(Editor::size(&gui), (400, 300));
+        assert_eq!(gui.evaluate("devicePixelRatio").unwrap(), json!(1));
+    }
 }
@@ -0,0 +1,383 @@
+// This module contains declarations of the GTK, WebKitGTK and Xlib functions
+// used by the Linux implementation.
+//
+// The libraries are loaded at runtime instead of being linked: a plugin must
+// still load in a host running on a system without WebKitGTK, in which case
+// 'Editor::open' simply fails.
+
+use std::error::Error;
+use std::os::raw::*;
+use std::sync::OnceLock;
+
+use libloading::Library;
+
+pub type gboolean = c_int;
+pub type gpointer = *mut c_void;
+pub type GType = usize;
+pub type GDestroyNotify = Option<unsafe extern "C" fn(gpointer)>;
+pub type GAsyncReadyCallback =
+    Option<unsafe extern "C" fn(gpointer, *mut GAsyncResult, gpointer)>;
+
+// We don't access fields of these types so we don't need exact declarations.
+pub enum Display {}
+pub enum GAsyncResult {}
+pub enum GCancellable {}
+pub enum GdkWindow {}
+pub enum GInputStream {}
+pub enum GtkWidget {}
+pub enum JSCValue {}
+pub enum SoupMessageHeaders {}
+pub enum WebKitJavascriptResult {}
+pub enum WebKitSecurityManager {}
+pub enum WebKitSettings {}
+pub enum WebKitURISchemeRequest {}
+pub enum WebKitURISchemeResponse {}
+pub enum WebKitUserContentManager {}
+pub enum WebKitUserScript {}
+pub enum WebKitWebContext {}
+
+pub type Window = c_ulong;
+
+pub const FALSE: gboolean = 0;
+pub const TRUE: gboolean = 1;
+
+pub type GQuark = u32;
+
+#[repr(C)]
+pub struct GError {
+    pub domain: GQuark,
+    pub code: c_int,
+    pub message: *mut c_char,
+}
+
+pub const G_IO_ERROR_NOT_FOUND: c_int = 1;
+
+pub const GTK_WINDOW_TOPLEVEL: c_int = 0;
+
+pub const SOUP_MESSAGE_HEADERS_RESPONSE: c_int = 1;
+
+pub const WEBKIT_USER_CONTENT_INJECT_TOP_FRAME: c_int = 1;
+pub const WEBKIT_USER_SCRIPT_INJECT_AT_DOCUMENT_START: c_int = 0;
+
+pub type WebKitURISchemeRequestCallback =
+    unsafe extern "C" fn(*mut WebKitURISchemeRequest, gpointer);
+
+pub struct Api {
+    // GLib, GObject and GIO
+    pub g_free: unsafe extern "C" fn(gpointer),
+    pub g_malloc: unsafe extern "C" fn(usize) -> gpointer,
+    pub g_object_new: unsafe extern "C" fn(GType, *const c_char, ...) -> gpointer,
+    pub g_object_unref: unsafe extern "C" fn(gpointer),
+    pub g_uri_unescape_string:
+        unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char,
+    pub g_memory_input_stream_new_from_data: unsafe extern "C" fn(
+        *const c_void, isize, GDestroyNotify) -> *mut GInputStream,
+    pub g_error_new_literal:
+        unsafe extern "C" fn(GQuark, c_int, *const c_char) -> *mut GError,
+    pub g_error_free: unsafe extern "C" fn(*mut GError),
+    pub g_io_error_quark: unsafe extern "C" fn() -> GQuark,
+
+    // GDK and GTK
+    pub gdk_set_allowed_backends: unsafe extern "C" fn(*const c_char),
+    pub gdk_screen_width: unsafe extern "C" fn() -> c_int,
+    pub gdk_screen_height: unsafe extern "C" fn() -> c_int,
+    pub gdk_x11_get_default_xdisplay: unsafe extern "C" fn() -> *mut Display,
+    pub gdk_x11_window_get_xid: unsafe extern "C" fn(*mut GdkWindow) -> Window,
+    pub gtk_init_check:
+        unsafe extern "C" fn(*mut c_int, *mut *mut *mut c_char) -> gboolean,
+    pub gtk_window_new: unsafe extern "C" fn(c_int) -> *mut GtkWidget,
+    pub gtk_window_set_decorated: unsafe extern "C" fn(*mut GtkWidget, gboolean),
+    pub gtk_window_resize: unsafe extern "C" fn(*mut GtkWidget, c_int, c_int),
+    pub gtk_container_add: unsafe extern "C" fn(*mut GtkWidget, *mut GtkWidget),
+    pub gtk_widget_realize: unsafe extern "C" fn(*mut GtkWidget),
+    pub gtk_widget_show_all: unsafe extern "C" fn(*mut GtkWidget),
+    pub gtk_widget_destroy: unsafe extern "C" fn(*mut GtkWidget),
+    pub gtk_widget_get_window:
+        unsafe extern "C" fn(*mut GtkWidget) -> *mut GdkWindow,
+    pub gtk_events_pending: unsafe extern "C" fn() -> gboolean,
+    pub gtk_main_iteration_do: unsafe extern "C" fn(gboolean) -> gboolean,
+    pub gtk_label_new: unsafe extern "C" fn(*const c_char) -> *mut GtkWidget,
+    pub gtk_label_set_selectable: unsafe extern "C" fn(*mut GtkWidget, gboolean),
+    pub gtk_label_set_line_wrap: unsafe extern "C" fn(*mut GtkWidget, gboolean),
+
+    // Xlib
+    pub XReparentWindow:
+        unsafe extern "C" fn(*mut Display, Window, Window, c_int, c_int) -> c_int,
+    pub XMapWindow: unsafe extern "C" fn(*mut Display, Window) -> c_int,
+    pub XFlush: unsafe extern "C" fn(*mut Display) -> c_int,
+
+    // The libraries must outlive the function pointers above.
+    _libraries: Vec<Library>,
+}
+
+// WebKitGTK is loaded separately so that GTK can be used to report that it's
+// missing.
+pub struct WebKitApi {
+    pub webkit_web_view_get_type: unsafe extern "C" fn() -> GType,
+    pub webkit_web_view_load_html:
+        unsafe extern "C" fn(*mut GtkWidget, *const c_char, *const c_char),
+    pub webkit_web_view_load_uri:
+        unsafe extern "C" fn(*mut GtkWidget, *const c_char),
+    pub webkit_web_view_run_javascript: unsafe extern "C" fn(
+        *mut GtkWidget,
+        *const c_char,
+        *mut GCancellable,
+        GAsyncReadyCallback,
+        gpointer),
+    pub webkit_web_view_run_javascript_finish: unsafe extern "C" fn(
+        *mut GtkWidget,
+        *mut GAsyncResult,
+        *mut *mut GError) -> *mut WebKitJavascriptResult,
+    pub webkit_javascript_result_get_js_value:
+        unsafe extern "C" fn(*mut WebKitJavascriptResult) -> *mut JSCValue,
+    pub webkit_javascript_result_unref:
+        unsafe extern "C" fn(*mut WebKitJavascriptResult),
+    // Provided by JavaScriptCoreGTK which WebKitGTK depends on.
+    pub jsc_value_to_string: unsafe extern "C" fn(*mut JSCValue) -> *mut c_char,
+    pub webkit_web_view_get_settings:
+        unsafe extern "C" fn(*mut GtkWidget) -> *mut WebKitSettings,
+    pub webkit_web_view_set_zoom_level:
+        unsafe extern "C" fn(*mut GtkWidget, c_double),
+    pub webkit_settings_set_enable_developer_extras:
+        unsafe extern "C" fn(*mut WebKitSettings, gboolean),
+    pub webkit_web_context_new: unsafe extern "C" fn() -> *mut WebKitWebContext,
+    pub webkit_web_context_register_uri_scheme: unsafe extern "C" fn(
+        *mut WebKitWebContext,
+        *const c_char,
+        WebKitURISchemeRequestCallback,
+        gpointer,
+        GDestroyNotify),
+    pub webkit_web_context_get_security_manager: unsafe extern "C" fn(
+        *mut WebKitWebContext) -> *mut WebKitSecurityManager,
+    pub webkit_security_manager_register_uri_scheme_as_secure:
+        unsafe extern "C" fn(*mut WebKitSecurityManager, *const c_char),
+    pub webkit_security_manager_register_uri_scheme_as_cors_enabled:
+        unsafe extern "C" fn(*mut WebKitSecurityManager, *const c_char),
+    pub webkit_uri_scheme_request_get_uri:
+        unsafe extern "C" fn(*mut WebKitURISchemeRequest) -> *const c_char,
+    pub webkit_uri_scheme_request_finish: unsafe extern "C" fn(
+        *mut WebKitURISchemeRequest, *mut GInputStream, i64, *const c_char),
+    pub webkit_uri_scheme_request_finish_error:
+        unsafe extern "C" fn(*mut WebKitURISchemeRequest, *mut GError),
+    pub webkit_user_content_manager_new:
+        unsafe extern "C" fn() -> *mut WebKitUserContentManager,
+    pub webkit_user_content_manager_add_script: unsafe extern "C" fn(
+        *mut WebKitUserContentManager, *mut WebKitUserScript),
+    pub webkit_user_script_new: unsafe extern "C" fn(
+        *const c_char,
+        c_int,
+        c_int,
+        *const *const c_char,
+        *const *const c_char) -> *mut WebKitUserScript,
+    pub webkit_user_script_unref: unsafe extern "C" fn(*mut WebKitUserScript),
+    // 'None' if WebKitGTK is older than 2.36.
+    pub uri_scheme_response: Option<UriSchemeResponseApi>,
+
+    // The library must outlive the function pointers above.
+    _library: Library,
+}
+
+// Allows responses to 'plugin' URIs to have HTTP headers.
+pub struct UriSchemeResponseApi {
+    pub webkit_uri_scheme_response_new: unsafe extern "C" fn(
+        *mut GInputStream, i64) -> *mut WebKitURISchemeResponse,
+    pub webkit_uri_scheme_response_set_content_type:
+        unsafe extern "C" fn(*mut WebKitURISchemeResponse, *const c_char),
+    pub webkit_uri_scheme_response_set_http_headers: unsafe extern "C" fn(
+        *mut WebKitURISchemeResponse, *mut SoupMessageHeaders),
+    pub webkit_uri_scheme_request_finish_with_response: unsafe extern "C" fn(
+        *mut WebKitURISchemeRequest, *mut WebKitURISchemeResponse),
+    // Provided by libsoup which WebKitGTK depends on. Both libsoup 2 and
+    // libsoup 3 declare these functions the same way.
+    pub soup_message_headers_new:
+        unsafe extern "C" fn(c_int) -> *mut SoupMessageHeaders,
+    pub soup_message_headers_append: unsafe extern "C" fn(
+        *mut SoupMessageHeaders, *const c_char, *const c_char),
+}
+
+unsafe fn symbol<T: Copy>(
+    library: &Library, name: &[u8]) -> Result<T, Box<dyn Error>>
+{
+    library
+        .get::<T>(name)
+        .map(|symbol| *symbol)
+        .map_err(|_| From::from(
+            format!(
+                "Couldn't find the '{}' function",
+                String::from_utf8_lossy(&name[..name.len() - 1]))))
+}
+
+fn open_library(names: &[&str]) -> Result<Library, Box<dyn Error>> {
+    names
+        .iter()
+        .filter_map(|name| Library::new(name).ok())
+        .next()
+        .ok_or_else(|| From::from(
+            format!("Couldn't load the '{}' library", names[0])))
+}
+
+impl Api {
+    fn load() -> Result<Api, Box<dyn Error>> {
+        let glib = open_library(&["libglib-2.0.so.0"])?;
+        let gobject = open_library(&["libgobject-2.0.so.0"])?;
+        let gio = open_library(&["libgio-2.0.so.0"])?;
+        let gdk = open_library(&["libgdk-3.so.0"])?;
+        let gtk = open_library(&["libgtk-3.so.0"])?;
+        let x11 = open_library(&["libX11.so.6"])?;
+
+        unsafe {
+            Ok(Api {
+                g_free: symbol(&glib, b"g_free\0")?,
+                g_malloc: symbol(&glib, b"g_malloc\0")?,
+                g_object_new: symbol(&gobject, b"g_object_new\0")?,
+                g_object_unref: symbol(&gobject, b"g_object_unref\0")?,
+                g_uri_unescape_string:
+                    symbol(&glib, b"g_uri_unescape_string\0")?,
+                g_memory_input_stream_new_from_data:
+                    symbol(&gio, b"g_memory_input_stream_new_from_data\0")?,
+                g_error_new_literal: symbol(&glib, b"g_error_new_literal\0")?,
+                g_error_free: symbol(&glib, b"g_error_free\0")?,
+                g_io_error_quark: symbol(&gio, b"g_io_error_quark\0")?,
+
+                gdk_set_allowed_backends:
+                    symbol(&gdk, b"gdk_set_allowed_backends\0")?,
+                gdk_screen_width: symbol(&gdk, b"gdk_screen_width\0")?,
+                gdk_screen_height: symbol(&gdk, b"gdk_screen_height\0")?,
+                gdk_x11_get_default_xdisplay:
+                    symbol(&gdk, b"gdk_x11_get_default_xdisplay\0")?,
+                gdk_x11_window_get_xid:
+                    symbol(&gdk, b"gdk_x11_window_get_xid\0")?,
+                gtk_init_check: symbol(&gtk, b"gtk_init_check\0")?,
+                gtk_window_new: symbol(&gtk, b"gtk_window_new\0")?,
+                gtk_window_set_decorated:
+                    symbol(&gtk, b"gtk_window_set_decorated\0")?,
+                gtk_window_resize: symbol(&gtk, b"gtk_window_resize\0")?,
+                gtk_container_add: symbol(&gtk, b"gtk_container_add\0")?,
+                gtk_widget_realize: symbol(&gtk, b"gtk_widget_realize\0")?,
+                gtk_widget_show_all: symbol(&gtk, b"gtk_widget_show_all\0")?,
+                gtk_widget_destroy: symbol(&gtk, b"gtk_widget_destroy\0")?,
+                gtk_widget_get_window:
+                    symbol(&gtk, b"gtk_widget_get_window\0")?,
+                gtk_events_pending: symbol(&gtk, b"gtk_events_pending\0")?,
+                gtk_main_iteration_do:
+                    symbol(&gtk, b"gtk_main_iteration_do\0")?,
+                gtk_label_new: symbol(&gtk, b"gtk_label_new\0")?,
+                gtk_label_set_selectable:
+                    symbol(&gtk, b"gtk_label_set_selectable\0")?,
+                gtk_label_set_line_wrap:
+                    symbol(&gtk, b"gtk_label_set_line_wrap\0")?,
+
+                XReparentWindow: symbol(&x11, b"XReparentWindow\0")?,
+                XMapWindow: symbol(&x11, b"XMapWindow\0")?,
+                XFlush: symbol(&x11, b"XFlush\0")?,
+
+                _libraries: vec![glib, gobject, gio, gdk, gtk, x11],
+            })
+        }
+    }
+}
+
+impl WebKitApi {
+    fn load() -> Result<WebKitApi, Box<dyn Error>> {
+        let webkit = open_library(
+            &["libwebkit2gtk-4.1.so.0", "libwebkit2gtk-4.0.so.37"])?;
+
+        unsafe {
+            Ok(WebKitApi {
+                webkit_web_view_get_type:
+                    symbol(&webkit, b"webkit_web_view_get_type\0")?,
+                webkit_web_view_load_html:
+                    symbol(&webkit, b"webkit_web_view_load_html\0")?,
+                webkit_web_view_load_uri:
+                    symbol(&webkit, b"webkit_web_view_load_uri\0")?,
+                webkit_web_view_run_javascript:
+                    symbol(&webkit, b"webkit_web_view_run_javascript\0")?,
+                webkit_web_view_run_javascript_finish: symbol(
+                    &webkit, b"webkit_web_view_run_javascript_finish\0")?,
+                webkit_javascript_result_get_js_value: symbol(
+                    &webkit, b"webkit_javascript_result_get_js_value\0")?,
+                webkit_javascript_result_unref:
+                    symbol(&webkit, b"webkit_javascript_result_unref\0")?,
+                jsc_value_to_string: symbol(&webkit, b"jsc_value_to_string\0")?,
+                webkit_web_view_get_settings:
+                    symbol(&webkit, b"webkit_web_view_get_settings\0")?,
+                webkit_web_view_set_zoom_level:
+                    symbol(&webkit, b"webkit_web_view_set_zoom_level\0")?,
+                webkit_settings_set_enable_developer_extras: symbol(
+                    &webkit, b"webkit_settings_set_enable_developer_extras\0")?,
+                webkit_web_context_new:
+                    symbol(&webkit, b"webkit_web_context_new\0")?,
+                webkit_web_context_register_uri_scheme: symbol(
+                    &webkit, b"webkit_web_context_register_uri_scheme\0")?,
+                webkit_web_context_get_security_manager: symbol(
+                    &webkit, b"webkit_web_context_get_security_manager\0")?,
+                webkit_security_manager_register_uri_scheme_as_secure: symbol(
+                    &webkit,
+                    b"webkit_security_manager_register_uri_scheme_as_secure\0")?,
+                webkit_security_manager_register_uri_scheme_as_cors_enabled:
+                    symbol(
+                        &webkit,
+                        b"webkit_security_manager_register_uri_scheme_as_cors_enabled\0")?,
+                webkit_uri_scheme_request_get_uri:
+                    symbol(&webkit, b"webkit_uri_scheme_request_get_uri\0")?,
+                webkit_uri_scheme_request_finish:
+                    symbol(&webkit, b"webkit_uri_scheme_request_finish\0")?,
+                webkit_uri_scheme_request_finish_error: symbol(
+                    &webkit, b"webkit_uri_scheme_request_finish_error\0")?,
+                webkit_user_content_manager_new:
+                    symbol(&webkit, b"webkit_user_content_manager_new\0")?,
+                webkit_user_content_manager_add_script: symbol(
+                    &webkit, b"webkit_user_content_manager_add_script\0")?,
+                webkit_user_script_new:
+                    symbol(&webkit, b"webkit_user_script_new\0")?,
+                webkit_user_script_unref:
+                    symbol(&webkit, b"webkit_user_script_unref\0")?,
+                uri_scheme_response: UriSchemeResponseApi::load(&webkit).ok(),
+
+                _library: webkit,
+            })
+        }
+    }
+}
+
+impl UriSchemeResponseApi {
+    unsafe fn load(
+        webkit: &Library) -> Result<UriSchemeResponseApi, Box<dyn Error>>
+    {
+        Ok(UriSchemeResponseApi {
+            webkit_uri_scheme_response_new:
+                symbol(webkit, b"webkit_uri_scheme_response_new\0")?,
+            webkit_uri_scheme_response_set_content_type: symbol(
+                webkit, b"webkit_uri_scheme_response_set_content_type\0")?,
+            webkit_uri_scheme_response_set_http_headers: symbol(
+                webkit, b"webkit_uri_scheme_response_set_http_headers\0")?,
+            webkit_uri_scheme_request_finish_with_response: symbol(
+                webkit,
+                b"webkit_uri_scheme_request_finish_with_response\0")?,
+            soup_message_headers_new:
+                symbol(webkit, b"soup_message_headers_new\0")?,
+            soup_message_headers_append:
+                symbol(webkit, b"soup_message_headers_append\0")?,
+        })
+    }
+}
+
+// These functions load the libraries on the first call. The results are
+// cached because the libraries can't be safely unloaded once GTK is
+// initialized.
+
+pub fn api() -> Result<&'static Api, Box<dyn Error>> {
+    static API: OnceLock<Result<Api, String>> = OnceLock::new();
+
+    API.get_or_init(|| Api::load().map_err(|error| error.to_string()))
+        .as_ref()
+        .map_err(|error| From::from(error.as_str()))
+}
+
+pub fn webkit_api() -> Result<&'static WebKitApi, Box<dyn Error>> {
+    static API: OnceLock<Result<WebKitApi, String>> = OnceLock::new();
+
+    API.get_or_init(|| WebKitApi::load().map_err(|error| error.to_string()))
+        .as_ref()
+        .map_err(|error| From::from(error.as_str()))
+}
@@ -0,0 +1,717 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_void};
+use std::ptr::{null, null_mut};
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use assets::{self, Asset, AssetProvider, BASE_URL};
+use error::{self, Error};
+use lib::{JavascriptCallback, Options, PluginGui};
+use linux::ffi;
+use linux::ffi::*;
+
+// Returns 'None' if the string contains null characters.
+fn c_string(value: &str) -> Option<CString> {
+    CString::new(value).ok()
+}
+
+// Documents are loaded with the 'BASE_URL' base URI so that requests to the
+// bridge are same-origin requests. Pages loaded from other URLs (e.g.
+// 'http://127.0.0.1:5173/') make cross-origin requests, which are allowed by
+// the 'Access-Control-Allow-Origin' header of the responses. WebKitGTK older
+// than 2.36 can't send the header, so the bridge works only in documents
+// there.
+const INVOKE_URI: &str = "plugin://app/external/invoke?";
+
+// Installs the 'external.invoke' function which synchronously passes its
+// argument to the 'JavascriptCallback' and returns the result.
+const EXTERNAL_SCRIPT: &str = r#"
+    window.external = {
+        invoke: function(message) {
+            var request = new XMLHttpRequest();
+            request.open(
+                "GET",
+                "plugin://app/external/invoke?" +
+                    encodeURIComponent(String(message)),
+                false);
+            request.send();
+            return request.responseText;
+        }
+    };
+"#;
+
+fn initialize(api: &Api) -> Result<(), Error> {
+    static INITIALIZED: OnceLock<bool> = OnceLock::new();
+
+    let initialized = *INITIALIZED.get_or_init(|| unsafe {
+        // The window is embedded using Xlib so other GDK backends won't work.
+        (api.gdk_set_allowed_backends)(b"x11\0".as_ptr() as *const c_char);
+        (api.gtk_init_check)(null_mut(), null_mut()) != FALSE
+    });
+
+    match initialized {
+        true => Ok(()),
+        false => Err(error::initialization("Couldn't initialize GTK")),
+    }
+}
+
+struct Window {
+    api: &'static Api,
+    handle: *mut GtkWidget,
+    size: (i32, i32),
+}
+
+impl Window {
+    pub fn new(
+        api: &'static Api,
+        parent: ffi::Window,
+        size: Option<(i32, i32)>) -> Window
+    {
+        let window_size = size.unwrap_or_else(|| Window::default_size(api));
+
+        let handle = unsafe {
+            let handle = (api.gtk_window_new)(GTK_WINDOW_TOPLEVEL);
+
+            (api.gtk_window_set_decorated)(handle, FALSE);
+            (api.gtk_window_resize)(handle, window_size.0, window_size.1);
+
+            // The window must be reparented before it's mapped, otherwise
+            // the window manager decorates it as a top-level window.
+            (api.gtk_widget_realize)(handle);
+            (api.XReparentWindow)(
+                (api.gdk_x11_get_default_xdisplay)(),
+                (api.gdk_x11_window_get_xid)(
+                    (api.gtk_widget_get_window)(handle)),
+                parent,
+                0, /*x*/
+                0 /*y*/);
+
+            handle
+        };
+
+        Window {
+            api,
+            handle,
+            size: window_size,
+        }
+    }
+
+    fn show(&self) {
+        unsafe {
+            (self.api.gtk_widget_show_all)(self.handle);
+
+            let display = (self.api.gdk_x11_get_default_xdisplay)();
+
+            (self.api.XMapWindow)(
+                display,
+                (self.api.gdk_x11_window_get_xid)(
+                    (self.api.gtk_widget_get_window)(self.handle)));
+            (self.api.XFlush)(display);
+        }
+    }
+
+    fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    // The web view fills the window so it's resized as well.
+    fn resize(&mut self, size: (i32, i32)) {
+        unsafe {
+            (self.api.gtk_window_resize)(self.handle, size.0, size.1);
+        }
+
+        self.size = size;
+    }
+
+    // Displays the text instead of the page. The text can be selected and
+    // copied.
+    fn add_error_panel(&self, text: &str) {
+        let text = c_string(&text.replace('\0', "")).unwrap();
+
+        unsafe {
+            let label = (self.api.gtk_label_new)(text.as_ptr());
+
+            (self.api.gtk_label_set_selectable)(label, TRUE);
+            (self.api.gtk_label_set_line_wrap)(label, TRUE);
+            (self.api.gtk_container_add)(self.handle, label);
+        }
+    }
+
+    fn default_size(api: &Api) -> (i32, i32) {
+        unsafe {
+            let width = (api.gdk_screen_width)() / 2;
+            let height = (api.gdk_screen_height)() / 2;
+
+            (width, height)
+        }
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        // This also destroys the web view.
+        unsafe {
+            (self.api.gtk_widget_destroy)(self.handle);
+        }
+    }
+}
+
+// The user data of the 'plugin' URI scheme handler.
+struct Bridge {
+    api: &'static Api,
+    webkit: &'static WebKitApi,
+    js_callback: Rc<JavascriptCallback>,
+    assets: Option<Rc<dyn AssetProvider>>,
+}
+
+impl Bridge {
+    unsafe fn invoke(&self, escaped_argument: &str) -> String {
+        // Null characters are always escaped in URIs.
+        let escaped_argument = c_string(escaped_argument).unwrap();
+        let argument = (self.api.g_uri_unescape_string)(
+            escaped_argument.as_ptr(), null());
+
+        if !argument.is_null() {
+            let argument_utf8 =
+                CStr::from_ptr(argument).to_string_lossy().into_owned();
+            (self.api.g_free)(argument as gpointer);
+            (self.js_callback)(argument_utf8)
+        } else {
+            String::new()
+        }
+    }
+
+    fn asset(&self, uri: &str) -> Option<Asset> {
+        let path = assets::resource_path(uri)?;
+
+        self.assets
+            .as_ref()
+            .and_then(|assets| assets.get(&path))
+    }
+}
+
+unsafe fn finish_request(
+    api: &Api,
+    webkit: &WebKitApi,
+    request: *mut WebKitURISchemeRequest,
+    asset: Asset)
+{
+    let content = &asset.content;
+    // The MIME type can't contain null characters.
+    let mime_type = c_string(&asset.mime_type)
+        .unwrap_or_else(|| c_string("application/octet-stream").unwrap());
+
+    // The stream takes ownership of the buffer and frees it with 'g_free'.
+    let buffer = (api.g_malloc)(content.len().max(1));
+    content.as_ptr().copy_to_nonoverlapping(buffer as *mut u8, content.len());
+
+    let stream = (api.g_memory_input_stream_new_from_data)(
+        buffer, content.len() as isize, Some(api.g_free));
+
+    match webkit.uri_scheme_response {
+        Some(ref functions) => {
+            let response = (functions.webkit_uri_scheme_response_new)(
+                stream, content.len() as i64);
+            let headers = (functions.soup_message_headers_new)(
+                SOUP_MESSAGE_HEADERS_RESPONSE);
+
+            (functions.soup_message_headers_append)(
+                headers,
+                b"Access-Control-Allow-Origin\0".as_ptr() as *const c_char,
+                b"*\0".as_ptr() as *const c_char);
+
+            (functions.webkit_uri_scheme_response_set_content_type)(
+                response, mime_type.as_ptr());
+            // The response takes ownership of the headers.
+            (functions.webkit_uri_scheme_response_set_http_headers)(
+                response, headers);
+            (functions.webkit_uri_scheme_request_finish_with_response)(
+                request, response);
+            (api.g_object_unref)(response as gpointer);
+        },
+        None => (webkit.webkit_uri_scheme_request_finish)(
+            request, stream, content.len() as i64, mime_type.as_ptr()),
+    }
+
+    (api.g_object_unref)(stream as gpointer);
+}
+
+unsafe extern "C" fn handle_uri_scheme_request(
+    request: *mut WebKitURISchemeRequest, user_data: gpointer)
+{
+    let bridge = &*(user_data as *const Bridge);
+    let api = bridge.api;
+    let webkit = bridge.webkit;
+
+    let uri = CStr::from_ptr((webkit.webkit_uri_scheme_request_get_uri)(request))
+        .to_string_lossy()
+        .into_owned();
+
+    let asset = if let Some(escaped_argument) = uri.strip_prefix(INVOKE_URI) {
+        Some(Asset {
+            content: bridge.invoke(escaped_argument).into_bytes().into(),
+            mime_type: "text/plain".into(),
+        })
+    } else {
+        bridge.asset(&uri)
+    };
+
+    match asset {
+        Some(asset) => finish_request(api, webkit, request, asset),
+        None => {
+            let message = c_string(&format!("'{}' is not found", uri))
+                .unwrap_or_default();
+            let error = (api.g_error_new_literal)(
+                (api.g_io_error_quark)(),
+                G_IO_ERROR_NOT_FOUND,
+                message.as_ptr());
+
+            (webkit.webkit_uri_scheme_request_finish_error)(request, error);
+            (api.g_error_free)(error);
+        }
+    }
+}
+
+unsafe extern "C" fn destroy_bridge(user_data: gpointer) {
+    drop(Box::from_raw(user_data as *mut Bridge));
+}
+
+// The state of a 'WebView::evaluate' call.
+struct Evaluation {
+    api: &'static Api,
+    webkit: &'static WebKitApi,
+    result: Option<Result<String, Error>>,
+}
+
+unsafe extern "C" fn finish_evaluation(
+    web_view: gpointer, result: *mut GAsyncResult, user_data: gpointer)
+{
+    let evaluation = &mut *(user_data as *mut Evaluation);
+    let api = evaluation.api;
+    let webkit = evaluation.webkit;
+
+    let mut error: *mut GError = null_mut();
+    let javascript_result = (webkit.webkit_web_view_run_javascript_finish)(
+        web_view as *mut GtkWidget, result, &mut error);
+
+    evaluation.result = Some(if !javascript_result.is_null() {
+        let value =
+            (webkit.webkit_javascript_result_get_js_value)(javascript_result);
+        let string = (webkit.jsc_value_to_string)(value);
+        let result = CStr::from_ptr(string).to_string_lossy().into_owned();
+
+        (api.g_free)(string as gpointer);
+        (webkit.webkit_javascript_result_unref)(javascript_result);
+        Ok(result)
+    } else if !error.is_null() {
+        let message =
+            CStr::from_ptr((*error).message).to_string_lossy().into_owned();
+        let code = (*error).code;
+
+        (api.g_error_free)(error);
+        Err(Error::ScriptEvaluation { message, code: Some(code) })
+    } else {
+        Err(error::evaluation("Evaluation of the Javascript code failed"))
+    });
+}
+
+struct WebView {
+    api: &'static Api,
+    webkit: &'static WebKitApi,
+    handle: *mut GtkWidget,
+}
+
+impl WebView {
+    fn new(
+        api: &'static Api,
+        webkit: &'static WebKitApi,
+        window: &Window,
+        html_document: String,
+        js_callback: Rc<JavascriptCallback>,
+        options: &Options) -> Result<WebView, Error>
+    {
+        let html_document = c_string(&html_document).ok_or_else(||
+            error::loading("The document contains a null character"))?;
+        let url = match options.url {
+            Some(ref url) => Some(c_string(url).ok_or_else(||
+                error::loading("The URL contains a null character"))?),
+            None => None,
+        };
+        // The crate's scripts run after the bridge.
+        let startup_script = [EXTERNAL_SCRIPT, &options.startup_script]
+            .join(";\n");
+        let startup_script = c_string(&startup_script).ok_or_else(||
+            error::loading("The script contains a null character"))?;
+
+        let handle = unsafe {
+            let scheme = b"plugin\0".as_ptr() as *const c_char;
+
+            // Every web view gets its own context because a URI scheme can
+            // be registered only once per context.
+            let context = (webkit.webkit_web_context_new)();
+            let bridge = Box::new(
+                Bridge {
+                    api,
+                    webkit,
+                    js_callback,
+                    assets: options.assets.clone(),
+                });
+
+            (webkit.webkit_web_context_register_uri_scheme)(
+                context,
+                scheme,
+                handle_uri_scheme_request,
+                Box::into_raw(bridge) as gpointer,
+                Some(destroy_bridge));
+
+            let security_manager =
+                (webkit.webkit_web_context_get_security_manager)(context);
+
+            (webkit.webkit_security_manager_register_uri_scheme_as_secure)(
+                security_manager, scheme);
+            (webkit.webkit_security_manager_register_uri_scheme_as_cors_enabled)(
+                security_manager, scheme);
+
+            let content_manager = (webkit.webkit_user_content_manager_new)();
+            let script = (webkit.webkit_user_script_new)(
+                startup_script.as_ptr(),
+                WEBKIT_USER_CONTENT_INJECT_TOP_FRAME,
+                WEBKIT_USER_SCRIPT_INJECT_AT_DOCUMENT_START,
+                null(),
+                null());
+
+            (webkit.webkit_user_content_manager_add_script)(
+                content_manager, script);
+            (webkit.webkit_user_script_unref)(script);
+
+            let handle = (api.g_object_new)(
+                (webkit.webkit_web_view_get_type)(),
+                b"web-context\0".as_ptr() as *const c_char,
+                context,
+                b"user-content-manager\0".as_ptr() as *const c_char,
+                content_manager,
+                null::<c_char>()) as *mut GtkWidget;
+
+            // The web view holds references to these objects.
+            (api.g_object_unref)(context as gpointer);
+            (api.g_object_unref)(content_manager as gpointer);
+
+            handle
+        };
+
+        if handle.is_null() {
+            return Err(error::initialization(
+                "Couldn't create a WebKitGTK web view"));
+        }
+
+        unsafe {
+            // The inspector is opened from the context menu.
+            (webkit.webkit_settings_set_enable_developer_extras)(
+                (webkit.webkit_web_view_get_settings)(handle),
+                options.developer_extras as gboolean);
+            (webkit.webkit_web_view_set_zoom_level)(
+                handle, options.zoom.unwrap_or(1.0));
+
+            (api.gtk_container_add)(window.handle, handle);
+        }
+
+        let web_view = WebView {
+            api,
+            webkit,
+            handle,
+        };
+
+        match url {
+            Some(url) => unsafe {
+                (webkit.webkit_web_view_load_uri)(handle, url.as_ptr());
+            },
+            None => web_view.load(&html_document),
+        }
+
+        Ok(web_view)
+    }
+
+    fn load(&self, html_document: &CStr) {
+        let base_uri = c_string(BASE_URL).unwrap();
+
+        unsafe {
+            (self.webkit.webkit_web_view_load_html)(
+                self.handle, html_document.as_ptr(), base_uri.as_ptr());
+        }
+    }
+
+    fn set_zoom(&self, zoom: f64) {
+        unsafe {
+            (self.webkit.webkit_web_view_set_zoom_level)(self.handle, zoom);
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        let javascript_code = c_string(javascript_code).ok_or_else(||
+            error::evaluation("The script contains a null character"))?;
+
+        unsafe {
+            (self.webkit.webkit_web_view_run_javascript)(
+                self.handle,
+                javascript_code.as_ptr(),
+                null_mut(),
+                None,
+                null_mut::<c_void>());
+        }
+
+        Ok(())
+    }
+
+    // WebKit evaluates scripts asynchronously, so the GTK main loop runs
+    // until the result is available.
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        let javascript_code = c_string(javascript_code).ok_or_else(||
+            error::evaluation("The script contains a null character"))?;
+
+        let mut evaluation = Evaluation {
+            api: self.api,
+            webkit: self.webkit,
+            result: None,
+        };
+
+        unsafe {
+            (self.webkit.webkit_web_view_run_javascript)(
+                self.handle,
+                javascript_code.as_ptr(),
+                null_mut(),
+                Some(finish_evaluation),
+                &mut evaluation as *mut Evaluation as gpointer);
+
+            // The callback is called even if the web view is destroyed.
+            while evaluation.result.is_none() {
+                (self.api.gtk_main_iteration_do)(TRUE);
+            }
+        }
+
+        evaluation.result.unwrap()
+    }
+}
+
+struct Gui {
+    html_document: String,
+    js_callback: Rc<JavascriptCallback>,
+    web_view: Option<WebView>,
+    window: Option<Window>,
+    options: Options,
+}
+
+impl PluginGui for Gui {
+    fn size(&self) -> (i32, i32) {
+        match self.window {
+            Some(ref window) => window.size(),
+            None => (0, 0)
+        }
+    }
+
+    fn position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn close(&mut self) {
+        self.web_view = None;
+        self.window = None;
+    }
+
+    fn open(&mut self, parent_handle: *mut c_void) -> Result<(), Error> {
+        let api = api()
+            .map_err(|error| error::initialization(&error.to_string()))?;
+
+        initialize(api)?;
+
+        // The host passes an X11 window ID instead of a pointer.
+        let window = Window::new(
+            api, parent_handle as ffi::Window, self.options.window_size);
+
+        let web_view = webkit_api()
+            .map_err(|error| error::initialization(&error.to_string()))
+            .and_then(|webkit| WebView::new(
+                api,
+                webkit,
+                &window,
+                self.html_document.clone(),
+                self.js_callback.clone(),
+                &self.options));
+
+        let result = match web_view {
+            Ok(web_view) => {
+                self.web_view = Some(web_view);
+                Ok(())
+            },
+            Err(error) => {
+                // The window is destroyed when it's dropped.
+                if !self.options.error_page {
+                    return Err(error);
+                }
+
+                window.add_error_panel(&error::report(&error));
+                Err(error)
+            }
+        };
+
+        window.show();
+        self.window = Some(window);
+
+        result
+    }
+
+    fn is_open(&mut self) -> bool {
+        self.window.is_some()
+    }
+
+    fn load(&mut self, html_document: String) -> Result<(), Error> {
+        let document = c_string(&html_document).ok_or_else(||
+            error::loading("The document contains a null character"))?;
+
+        self.html_document = html_document;
+        self.options.url = None;
+
+        if let Some(ref web_view) = self.web_view {
+            web_view.load(&document);
+        }
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        self.options.window_size = Some((width, height));
+
+        if let Some(ref mut window) = self.window {
+            window.resize((width, height));
+        }
+    }
+
+    // GTK scales the whole window including the web view by itself, so the
+    // system scale factor is left as is.
+    fn set_zoom(&mut self, zoom: f64) {
+        self.options.zoom = Some(zoom);
+
+        if let Some(ref web_view) = self.web_view {
+            web_view.set_zoom(zoom);
+        }
+    }
+
+    fn idle(&mut self) {
+        // Hosts don't run the GTK main loop so we process pending events
+        // when the host gives us time.
+        if let Some(ref window) = self.window {
+            unsafe {
+                while (window.api.gtk_events_pending)() != FALSE {
+                    (window.api.gtk_main_iteration_do)(FALSE);
+                }
+            }
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        if let Some(ref web_view) = self.web_view {
+            web_view.execute(javascript_code)
+        } else {
+            Err(Error::WindowClosed)
+        }
+    }
+
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        if let Some(ref web_view) = self.web_view {
+            web_view.evaluate(javascript_code)
+        } else {
+            Err(Error::WindowClosed)
+        }
+    }
+}
+
+pub fn new_plugin_gui(
+    html_document: String,
+    js_callback: JavascriptCallback,
+    options: Options) -> Box<dyn PluginGui>
+{
+    Box::new(
+        Gui {
+            html_document,
+            js_callback: Rc::new(js_callback),
+            web_view: None,
+            window: None,
+            options,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    // Processes GTK events until the callback receives the message.
+    fn wait_for(
+        gui: &mut dyn PluginGui,
+        messages: &RefCell<Vec<String>>,
+        message: &str) -> bool
+    {
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while Instant::now() < deadline {
+            if messages.borrow().iter().any(|received| received == message) {
+                return true;
+            }
+
+            gui.idle();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        false
+    }
+
+    // Requires an X server and WebKitGTK, so it's run separately, e.g.:
+    //
+    //     xvfb-run cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn bridge_works_in_a_dummy_parent_window() {
+        let api = api().unwrap();
+        initialize(api).unwrap();
+
+        // Hosts pass the X11 IDs of their own windows.
+        let parent = unsafe {
+            let handle = (api.gtk_window_new)(GTK_WINDOW_TOPLEVEL);
+            (api.gtk_widget_realize)(handle);
+            handle
+        };
+        let parent_id = unsafe {
+            (api.gdk_x11_window_get_xid)((api.gtk_widget_get_window)(parent))
+        };
+
+        let messages = Rc::new(RefCell::new(Vec::new()));
+        let callback_messages = messages.clone();
+
+        let mut gui = new_plugin_gui(
+            "<script>external.invoke('loaded');</script>".to_string(),
+            Box::new(move |message| {
+                let result = format!("{} received", message);
+                callback_messages.borrow_mut().push(message);
+                result
+            }),
+            Options::default());
+
+        gui.open(parent_id as *mut c_void).unwrap();
+        assert!(wait_for(&mut *gui, &messages, "loaded"));
+
+        gui.execute("external.invoke('executed');").unwrap();
+        assert!(wait_for(&mut *gui, &messages, "executed"));
+
+        assert_eq!(
+            gui.evaluate("external.invoke('evaluated')").unwrap(),
+            "evaluated received");
+
+        gui.close();
+
+        unsafe {
+            (api.gtk_widget_destroy)(parent);
+        }
+    }
+}
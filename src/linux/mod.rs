@@ -0,0 +1,5 @@
+#[allow(dead_code, non_camel_case_types, non_snake_case)]
+mod ffi;
+mod gui;
+
+pub use linux::gui::new_plugin_gui;
@@ -0,0 +1,381 @@
+// Exposes the plugin's 'PluginParameters' to the page.
+//
+// The page accesses parameters through the 'vst.parameters' object defined in
+// 'SCRIPT'. Changes made by the host (e.g. automation) are detected on idle
+// and passed to the listeners registered with
+// 'vst.parameters.addListener(function(index, value) {...})'.
+//
+// User gestures should be reported with 'vst.beginEdit(index)',
+// 'vst.setParameter(index, value)' and 'vst.endEdit(index)', which also
+// notify the host (if any) so that it can record automation.
+
+use std::cell::RefCell;
+use std::ptr::null_mut;
+use std::sync::Arc;
+
+use vst::host::{Host, OpCode};
+use vst::plugin::{HostCallback, PluginParameters};
+
+use events;
+use lib::PluginGui;
+
+// Messages starting with this prefix are reserved for the binding and never
+// reach the application callback.
+const MESSAGE_PREFIX: &str = "vst-gui:parameters.";
+
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    vst.parameters = {
+        listeners: [],
+        count: function() {
+            return parseInt(external.invoke("vst-gui:parameters.count"), 10);
+        },
+        get: function(index) {
+            return parseFloat(
+                external.invoke("vst-gui:parameters.get " + index));
+        },
+        set: function(index, value) {
+            external.invoke("vst-gui:parameters.set " + index + " " + value);
+        },
+        getName: function(index) {
+            return external.invoke("vst-gui:parameters.getName " + index);
+        },
+        getText: function(index) {
+            return external.invoke("vst-gui:parameters.getText " + index);
+        },
+        getLabel: function(index) {
+            return external.invoke("vst-gui:parameters.getLabel " + index);
+        },
+        addListener: function(listener) {
+            vst.parameters.listeners.push(listener);
+        },
+        notify: function(index, value) {
+            for (var i = 0; i < vst.parameters.listeners.length; ++i) {
+                vst.parameters.listeners[i](index, value);
+            }
+        }
+    };
+    vst.beginEdit = function(index) {
+        external.invoke("vst-gui:parameters.beginEdit " + index);
+    };
+    vst.setParameter = function(index, value) {
+        external.invoke(
+            "vst-gui:parameters.setParameter " + index + " " + value);
+    };
+    vst.endEdit = function(index) {
+        external.invoke("vst-gui:parameters.endEdit " + index);
+    };
+"#;
+
+pub struct Binding {
+    parameters: Arc<dyn PluginParameters>,
+    parameter_count: i32,
+    host: Option<HostCallback>,
+    // The values the page is known to display.
+    values: RefCell<Vec<f32>>,
+}
+
+impl Binding {
+    pub fn new(
+        parameters: Arc<dyn PluginParameters>,
+        parameter_count: i32,
+        host: Option<HostCallback>) -> Binding
+    {
+        let binding = Binding {
+            parameters,
+            parameter_count,
+            host,
+            values: RefCell::new(Vec::new()),
+        };
+
+        binding.reset();
+        binding
+    }
+
+    // Forgets changes which were not passed to the page. This is used when
+    // the page is (re)loaded because it reads current values itself.
+    pub fn reset(&self) {
+        *self.values.borrow_mut() = (0..self.parameter_count)
+            .map(|index| self.parameters.get_parameter(index))
+            .collect();
+    }
+
+    fn is_valid_index(&self, index: i32) -> bool {
+        index >= 0 && index < self.parameter_count
+    }
+
+    fn set_parameter(&self, index: i32, value: f32) {
+        self.parameters.set_parameter(index, value);
+        // The page already knows about this change.
+        self.values.borrow_mut()[index as usize] =
+            self.parameters.get_parameter(index);
+    }
+
+    // The 'Host' trait of the 'vst' crate doesn't support these opcodes.
+    fn call_host(&self, opcode: OpCode, index: i32) {
+        let host = match self.host {
+            Some(ref host) => host,
+            None => return,
+        };
+
+        if let Some(callback) = host.raw_callback() {
+            callback(host.raw_effect(), opcode.into(), index, 0, null_mut(), 0.0);
+        }
+    }
+
+    // Returns 'None' for messages which are not addressed to the binding.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        let command = message.strip_prefix(MESSAGE_PREFIX)?;
+
+        let mut tokens = command.split_whitespace();
+
+        let name = tokens.next().unwrap_or("");
+        let index = tokens
+            .next()
+            .and_then(|token| token.parse::<i32>().ok())
+            .filter(|&index| self.is_valid_index(index));
+        let value = tokens.next().and_then(|token| token.parse::<f32>().ok());
+
+        let result = match (name, index, value) {
+            ("count", _, _) => self.parameter_count.to_string(),
+            ("get", Some(index), _) =>
+                self.parameters.get_parameter(index).to_string(),
+            ("set", Some(index), Some(value)) => {
+                self.set_parameter(index, value);
+                String::new()
+            },
+            ("setParameter", Some(index), Some(value)) => {
+                self.set_parameter(index, value);
+
+                if let Some(ref host) = self.host {
+                    host.automate(index, value);
+                }
+
+                String::new()
+            },
+            ("beginEdit", Some(index), _) => {
+                self.call_host(OpCode::BeginEdit, index);
+                String::new()
+            },
+            ("endEdit", Some(index), _) => {
+                self.call_host(OpCode::EndEdit, index);
+                String::new()
+            },
+            ("getName", Some(index), _) =>
+                self.parameters.get_parameter_name(index),
+            ("getText", Some(index), _) =>
+                self.parameters.get_parameter_text(index),
+            ("getLabel", Some(index), _) =>
+                self.parameters.get_parameter_label(index),
+            _ => String::new(),
+        };
+
+        Some(result)
+    }
+
+    // Passes parameter changes made outside of the page to the page.
+    pub fn synchronize(&self, gui: &dyn PluginGui) {
+        // The values must not be borrowed while the page runs scripts since
+        // the scripts can set parameters.
+        let changes: Vec<(usize, f32)> = self.values
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, &known_value)| (
+                index, known_value, self.parameters.get_parameter(index as i32)))
+            // Compared bitwise so that NaN values are not notified again.
+            .filter(|&(_, known_value, value)| {
+                value.to_bits() != known_value.to_bits()
+            })
+            .map(|(index, _, value)| (index, value))
+            .collect();
+
+        for (index, value) in changes {
+            // Infinite and NaN values are passed as 'null'.
+            let javascript_code = format!(
+                "vst.parameters.notify({}, {});",
+                index,
+                events::to_javascript(&value)
+                    .expect("Numbers are always serializable"));
+
+            if gui.execute(&javascript_code).is_ok() {
+                self.values.borrow_mut()[index] = value;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "headless")]
+    use std::rc::Rc;
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct Parameters {
+        values: Mutex<Vec<f32>>,
+    }
+
+    impl Parameters {
+        fn new(values: &[f32]) -> Arc<Parameters> {
+            Arc::new(Parameters { values: Mutex::new(values.to_vec()) })
+        }
+    }
+
+    impl PluginParameters for Parameters {
+        fn get_parameter(&self, index: i32) -> f32 {
+            self.values.lock().unwrap()[index as usize]
+        }
+
+        fn set_parameter(&self, index: i32, value: f32) {
+            self.values.lock().unwrap()[index as usize] = value;
+        }
+
+        fn get_parameter_name(&self, index: i32) -> String {
+            format!("Parameter {}", index)
+        }
+
+        fn get_parameter_text(&self, index: i32) -> String {
+            format!("{:.1}", self.get_parameter(index))
+        }
+
+        fn get_parameter_label(&self, _index: i32) -> String {
+            "dB".to_string()
+        }
+    }
+
+    #[test]
+    fn handle_exposes_the_parameters() {
+        let parameters = Parameters::new(&[0.25, 0.5]);
+        let binding = Binding::new(parameters.clone(), 2, None);
+        let handle = |message: &str| binding.handle(message).unwrap();
+
+        assert_eq!(handle("vst-gui:parameters.count"), "2");
+        assert_eq!(handle("vst-gui:parameters.get 1"), "0.5");
+        assert_eq!(handle("vst-gui:parameters.getName 0"), "Parameter 0");
+        assert_eq!(handle("vst-gui:parameters.getText 0"), "0.2");
+        assert_eq!(handle("vst-gui:parameters.getLabel 0"), "dB");
+
+        assert_eq!(handle("vst-gui:parameters.set 0 0.75"), "");
+        assert_eq!(parameters.get_parameter(0), 0.75);
+
+        // Invalid indices are ignored.
+        assert_eq!(handle("vst-gui:parameters.get 2"), "");
+        assert_eq!(handle("vst-gui:parameters.set -1 0.5"), "");
+        assert_eq!(binding.handle("getName 0"), None);
+    }
+
+    // Opens the document in the headless backend. The binding handles its
+    // messages before the application callback like in 'PluginGui'.
+    #[cfg(feature = "headless")]
+    fn open_page(
+        binding: Rc<Binding>,
+        html_document: &str,
+        js_callback: ::lib::JavascriptCallback) -> Box<dyn PluginGui>
+    {
+        use headless;
+        use script;
+
+        let mut gui = headless::new_plugin_gui(
+            script::inject(html_document, SCRIPT),
+            Box::new(move |message: String| binding
+                .handle(&message)
+                .unwrap_or_else(|| js_callback(message))),
+            Default::default());
+
+        gui.open(null_mut()).unwrap();
+        gui
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn synchronize_notifies_the_page_about_changes() {
+        let parameters = Parameters::new(&[0.25, 0.5]);
+        let binding = Rc::new(Binding::new(parameters.clone(), 2, None));
+        let notifications = Rc::new(RefCell::new(Vec::new()));
+        let page_notifications = notifications.clone();
+
+        let gui = open_page(
+            binding.clone(),
+            "<script>\
+            vst.parameters.addListener(function(index, value) {\
+                external.invoke(index + \" \" + value);\
+            });\
+            </script>",
+            Box::new(move |message| {
+                page_notifications.borrow_mut().push(message);
+                String::new()
+            }));
+
+        parameters.set_parameter(1, 1.0);
+        binding.synchronize(&*gui);
+        binding.synchronize(&*gui);
+        assert_eq!(*notifications.borrow(), vec!["1 1"]);
+
+        // Changes made by the page are not passed back to it.
+        binding.handle("vst-gui:parameters.set 0 0.75");
+        binding.synchronize(&*gui);
+        assert_eq!(*notifications.borrow(), vec!["1 1"]);
+    }
+
+    #[cfg(feature = "headless")]
+    #[test]
+    fn gestures_are_reported_to_the_host() {
+        use std::mem::MaybeUninit;
+        use std::os::raw::c_void;
+        use std::ptr::addr_of_mut;
+
+        use vst::api::consts::VST_MAGIC;
+        use vst::api::AEffect;
+
+        thread_local! {
+            static HOST_CALLS: RefCell<Vec<(i32, i32, f32)>> =
+                const { RefCell::new(Vec::new()) };
+        }
+
+        fn host_callback(
+            _effect: *mut AEffect,
+            opcode: i32,
+            index: i32,
+            _value: isize,
+            _ptr: *mut c_void,
+            opt: f32) -> isize
+        {
+            HOST_CALLS.with(|calls|
+                calls.borrow_mut().push((opcode, index, opt)));
+            0
+        }
+
+        // The host only checks the magic number of the effect.
+        let mut effect = MaybeUninit::<AEffect>::uninit();
+        unsafe {
+            addr_of_mut!((*effect.as_mut_ptr()).magic).write(VST_MAGIC);
+        }
+        let host = HostCallback::wrap(host_callback, effect.as_mut_ptr());
+
+        let parameters = Parameters::new(&[0.25, 0.5]);
+        let binding = Rc::new(Binding::new(parameters.clone(), 2, Some(host)));
+
+        let gui = open_page(binding, "", Box::new(|_| String::new()));
+
+        gui.execute(
+            "vst.beginEdit(1);\
+            vst.setParameter(1, 0.75);\
+            vst.setParameter(1, 1);\
+            vst.endEdit(1);\
+            vst.parameters.set(0, 1);").unwrap();
+
+        let code = |opcode: OpCode| -> i32 { opcode.into() };
+        assert_eq!(
+            HOST_CALLS.with(|calls| calls.borrow().clone()),
+            vec![
+                (code(OpCode::BeginEdit), 1, 0.0),
+                (code(OpCode::Automate), 1, 0.75),
+                (code(OpCode::Automate), 1, 1.0),
+                (code(OpCode::EndEdit), 1, 0.0),
+            ]);
+        assert_eq!(parameters.get_parameter(0), 1.0);
+        assert_eq!(parameters.get_parameter(1), 1.0);
+    }
+}
@@ -0,0 +1,116 @@
+// The development mode enabled with 'PluginGuiBuilder::development_directory'
+// and the 'hot-reload' feature: the document is loaded from 'index.html' in a
+// directory, the other files of the directory are served as assets, and the
+// page is reloaded in place when the files change.
+//
+// The directory is checked for changes on 'Editor::idle' at most twice a
+// second. Plugins usually enable the feature only in their development
+// builds, e.g. with their own feature:
+//
+//     [features]
+//     development = ["vst-gui/hot-reload"]
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use assets::{self, Asset, AssetProvider};
+use error::{self, Error};
+
+const DOCUMENT_NAME: &str = "index.html";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub fn read_document(directory: &Path) -> Result<String, Error> {
+    let path = directory.join(DOCUMENT_NAME);
+
+    fs::read_to_string(&path).map_err(|error| error::loading(&format!(
+        "Couldn't read '{}': {}", path.display(), error)))
+}
+
+// Serves the files of the directory as they are when the page requests them.
+pub struct DirectoryAssets {
+    directory: PathBuf,
+}
+
+impl DirectoryAssets {
+    pub fn new(directory: &Path) -> DirectoryAssets {
+        DirectoryAssets {
+            directory: directory.to_path_buf(),
+        }
+    }
+}
+
+impl AssetProvider for DirectoryAssets {
+    fn get(&self, path: &str) -> Option<Asset> {
+        // The page must not read files outside of the directory.
+        let is_nested = Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+
+        if !is_nested {
+            return None;
+        }
+
+        fs::read(self.directory.join(path)).ok().map(|content| Asset {
+            content: content.into(),
+            mime_type: assets::mime_type(path).into(),
+        })
+    }
+}
+
+// Returns the latest modification time of the directory, its files and its
+// subdirectories. Adding or removing a file modifies its directory.
+fn last_modification(path: &Path) -> Option<SystemTime> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok();
+
+    if !metadata.is_dir() {
+        return modified;
+    }
+
+    fs::read_dir(path)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| last_modification(&entry.path()))
+        .chain(modified)
+        .max()
+}
+
+pub struct Watcher {
+    directory: PathBuf,
+    last_modification: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl Watcher {
+    pub fn new(directory: PathBuf) -> Watcher {
+        Watcher {
+            last_modification: last_modification(&directory),
+            directory,
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // Returns 'true' if the directory has changed since the last call.
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+
+        self.last_poll = Instant::now();
+
+        let last_modification = last_modification(&self.directory);
+
+        if last_modification == self.last_modification {
+            return false;
+        }
+
+        self.last_modification = last_modification;
+        true
+    }
+}
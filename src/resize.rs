@@ -0,0 +1,349 @@
+// The size and the scale factor of the editor window.
+//
+// Sizes are logical: they are measured in CSS pixels and converted to
+// physical pixels with the scale factor before they reach the host or the
+// window. Sizes are adjusted to the constraints, the host is asked to resize
+// its window with the 'sizeWindow' opcode, and the page receives the
+// 'resize' event with the new size. Requests made by the page with
+// 'vst.resize(width, height)' are applied on 'Editor::idle' because the
+// window can't be resized while the page is calling us.
+//
+// Hosts answer 'sizeWindow' with 1 when they resize their windows, but many
+// hosts answer 0 anyway. The answer is ignored unless
+// 'PluginGuiBuilder::strict_host_resize' is set.
+//
+// The page reads the scale factor from 'vst.scaleFactor' and receives the
+// 'scale' event when it changes.
+
+use std::cell::Cell;
+use std::ptr::null_mut;
+
+use vst::host::OpCode;
+use vst::plugin::HostCallback;
+
+use error::{self, Error};
+use lib::PluginGui;
+
+const MESSAGE_PREFIX: &str = "vst-gui:resize ";
+const SCALE_MESSAGE: &str = "vst-gui:scale";
+
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    vst.resize = function(width, height) {
+        external.invoke(
+            "vst-gui:resize " + Math.round(width) + " " + Math.round(height));
+    };
+    Object.defineProperty(vst, "scaleFactor", {
+        get: function() {
+            return Number(external.invoke("vst-gui:scale"));
+        }
+    });
+"#;
+
+pub fn validate_scale_factor(scale_factor: f64) -> Result<(), Error> {
+    match scale_factor.is_finite() && scale_factor > 0.0 {
+        true => Ok(()),
+        false => Err(error::configuration(
+            "The scale factor must be a positive number")),
+    }
+}
+
+pub fn to_physical(size: (i32, i32), scale_factor: f64) -> (i32, i32) {
+    ((size.0 as f64 * scale_factor).round() as i32,
+        (size.1 as f64 * scale_factor).round() as i32)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SizeConstraints {
+    pub min_size: Option<(i32, i32)>,
+    pub max_size: Option<(i32, i32)>,
+    // The width divided by the height.
+    pub aspect_ratio: Option<f64>,
+}
+
+impl SizeConstraints {
+    pub fn validate(&self) -> Result<(), Error> {
+        let is_positive = |size: Option<(i32, i32)>| size
+            .map(|(width, height)| width > 0 && height > 0)
+            .unwrap_or(true);
+
+        if !is_positive(self.min_size) || !is_positive(self.max_size) {
+            return Err(error::configuration(
+                "The size limits must be positive"));
+        }
+
+        if let (Some(min_size), Some(max_size)) =
+            (self.min_size, self.max_size)
+        {
+            if min_size.0 > max_size.0 || min_size.1 > max_size.1 {
+                return Err(error::configuration(
+                    "The minimum size exceeds the maximum size"));
+            }
+        }
+
+        match self.aspect_ratio {
+            Some(ratio) if !(ratio.is_finite() && ratio > 0.0) =>
+                Err(error::configuration(
+                    "The aspect ratio must be a positive number")),
+            _ => Ok(()),
+        }
+    }
+
+    // Returns the closest size satisfying the constraints. The limits take
+    // precedence over the aspect ratio.
+    pub fn apply(&self, size: (i32, i32)) -> (i32, i32) {
+        let (mut width, mut height) = size;
+
+        if let Some(ratio) = self.aspect_ratio {
+            height = (width as f64 / ratio).round() as i32;
+        }
+
+        if let Some((min_width, min_height)) = self.min_size {
+            width = width.max(min_width);
+            height = height.max(min_height);
+        }
+
+        if let Some((max_width, max_height)) = self.max_size {
+            width = width.min(max_width);
+            height = height.min(max_height);
+        }
+
+        (width.max(1), height.max(1))
+    }
+}
+
+pub struct Resizer {
+    constraints: SizeConstraints,
+    host: Option<HostCallback>,
+    // Treats 0 returned by 'sizeWindow' as a refusal.
+    strict_host_resize: bool,
+    // The last logical size set with 'PluginGuiBuilder::size' or
+    // 'PluginGui::resize'. 'None' stands for the backend's default size.
+    size: Cell<Option<(i32, i32)>>,
+    // 'None' stands for the system scale factor until 'initialize' is called.
+    scale_factor: Cell<Option<f64>>,
+    request: Cell<Option<(i32, i32)>>,
+}
+
+impl Resizer {
+    pub fn new(
+        constraints: SizeConstraints,
+        host: Option<HostCallback>,
+        strict_host_resize: bool,
+        size: Option<(i32, i32)>,
+        scale_factor: Option<f64>) -> Resizer
+    {
+        Resizer {
+            constraints,
+            host,
+            strict_host_resize,
+            size: Cell::new(size.map(|size| constraints.apply(size))),
+            scale_factor: Cell::new(scale_factor),
+            request: Cell::new(None),
+        }
+    }
+
+    // Applies the scale factor and the initial size to the new backend.
+    pub fn initialize(&self, gui: &mut dyn PluginGui) {
+        let scale_factor = self.scale_factor.get()
+            .unwrap_or_else(|| gui.system_scale_factor());
+
+        self.set_scale_factor(gui, scale_factor);
+
+        if let Some(size) = self.size.get() {
+            let (width, height) = self.to_physical(size);
+            gui.resize(width, height);
+        }
+    }
+
+    pub fn constraints(&self) -> &SizeConstraints {
+        &self.constraints
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor.get().unwrap_or(1.0)
+    }
+
+    // Zooms the page so that a CSS pixel takes 'scale_factor' physical
+    // pixels. The window is not resized.
+    pub fn set_scale_factor(
+        &self, gui: &mut dyn PluginGui, scale_factor: f64)
+    {
+        self.scale_factor.set(Some(scale_factor));
+        gui.set_zoom(scale_factor / gui.system_scale_factor());
+    }
+
+    // Returns the logical size of the window. It's known only if the window
+    // is open or its size was set explicitly.
+    pub fn size(&self, gui: &dyn PluginGui) -> Option<(i32, i32)> {
+        self.size.get().or_else(|| {
+            let (width, height) = gui.size();
+            let scale_factor = self.scale_factor();
+
+            match width > 0 && height > 0 {
+                true => Some((
+                    (width as f64 / scale_factor).round() as i32,
+                    (height as f64 / scale_factor).round() as i32)),
+                false => None,
+            }
+        })
+    }
+
+    pub fn set_size(&self, size: (i32, i32)) {
+        self.size.set(Some(size));
+    }
+
+    pub fn to_physical(&self, size: (i32, i32)) -> (i32, i32) {
+        to_physical(size, self.scale_factor())
+    }
+
+    // Returns the size requested by the page since the last call.
+    pub fn take_request(&self) -> Option<(i32, i32)> {
+        self.request.take()
+    }
+
+    // The size is physical. Returns 'false' if the host refused to resize its
+    // window, which is known only with 'strict_host_resize'. Without the host
+    // the window is resized anyway.
+    pub fn notify_host(&self, size: (i32, i32)) -> bool {
+        let host = match self.host {
+            Some(ref host) => host,
+            None => return true,
+        };
+
+        let result = match host.raw_callback() {
+            Some(callback) => callback(
+                host.raw_effect(),
+                OpCode::SizeWindow.into(),
+                size.0,
+                size.1 as isize,
+                null_mut(),
+                0.0),
+            None => return true,
+        };
+
+        result != 0 || !self.strict_host_resize
+    }
+
+    // Returns 'None' for messages which are not addressed to this object.
+    pub fn handle(&self, message: &str) -> Option<String> {
+        if message == SCALE_MESSAGE {
+            return Some(self.scale_factor().to_string());
+        }
+
+        let arguments = message.strip_prefix(MESSAGE_PREFIX)?;
+
+        let mut tokens = arguments
+            .split_whitespace()
+            .map(|token| token.parse::<i32>().ok());
+
+        if let (Some(Some(width)), Some(Some(height))) =
+            (tokens.next(), tokens.next())
+        {
+            self.request.set(Some((width, height)));
+        }
+
+        Some(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::raw::c_void;
+
+    use vst::api::{AEffect, HostCallbackProc};
+
+    use super::*;
+
+    #[test]
+    fn validate_rejects_invalid_constraints() {
+        let valid = SizeConstraints {
+            min_size: Some((100, 50)),
+            max_size: Some((800, 600)),
+            aspect_ratio: Some(2.0),
+        };
+        assert!(valid.validate().is_ok());
+        assert!(SizeConstraints::default().validate().is_ok());
+
+        let invalid = [
+            SizeConstraints { min_size: Some((0, 50)), ..valid },
+            SizeConstraints { max_size: Some((800, -1)), ..valid },
+            SizeConstraints { min_size: Some((900, 50)), ..valid },
+            SizeConstraints { aspect_ratio: Some(0.0), ..valid },
+            SizeConstraints { aspect_ratio: Some(f64::NAN), ..valid },
+        ];
+
+        for constraints in invalid.iter() {
+            match constraints.validate() {
+                Err(Error::Configuration { .. }) => {},
+                result => panic!("{:?} for {:?}", result, constraints),
+            }
+        }
+    }
+
+    #[test]
+    fn apply_limits_the_size() {
+        let constraints = SizeConstraints {
+            min_size: Some((100, 50)),
+            max_size: Some((800, 600)),
+            aspect_ratio: None,
+        };
+
+        assert_eq!(constraints.apply((400, 300)), (400, 300));
+        assert_eq!(constraints.apply((10, 1000)), (100, 600));
+        assert_eq!(constraints.apply((1000, 10)), (800, 50));
+    }
+
+    #[test]
+    fn apply_keeps_the_aspect_ratio_within_the_limits() {
+        let constraints = SizeConstraints {
+            min_size: None,
+            max_size: Some((800, 300)),
+            aspect_ratio: Some(2.0),
+        };
+
+        assert_eq!(constraints.apply((401, 100)), (401, 201));
+        // The limits take precedence.
+        assert_eq!(constraints.apply((800, 800)), (800, 300));
+    }
+
+    #[test]
+    fn apply_returns_positive_sizes() {
+        assert_eq!(SizeConstraints::default().apply((0, -5)), (1, 1));
+    }
+
+    fn host(answer: HostCallbackProc) -> HostCallback {
+        HostCallback::wrap(answer, null_mut())
+    }
+
+    fn accepting_host(
+        _: *mut AEffect, opcode: i32, width: i32, height: isize,
+        _: *mut c_void, _: f32) -> isize
+    {
+        let size_window: i32 = OpCode::SizeWindow.into();
+
+        assert_eq!(opcode, size_window);
+        assert_eq!((width, height), (300, 200));
+        1
+    }
+
+    fn silent_host(
+        _: *mut AEffect, _: i32, _: i32, _: isize, _: *mut c_void, _: f32)
+        -> isize
+    {
+        0
+    }
+
+    #[test]
+    fn notify_host_ignores_the_answer_by_default() {
+        let resizer = |host, strict| Resizer::new(
+            SizeConstraints::default(), host, strict, None, None);
+
+        assert!(resizer(None, true).notify_host((300, 200)));
+        assert!(resizer(Some(host(silent_host)), false).notify_host((300, 200)));
+        assert!(resizer(Some(host(accepting_host)), true)
+            .notify_host((300, 200)));
+        assert!(!resizer(Some(host(silent_host)), true)
+            .notify_host((300, 200)));
+    }
+}
@@ -0,0 +1,201 @@
+// A JSON-RPC style layer on top of 'JavascriptCallback'.
+//
+// Requests are sent by the 'rpc.call(method, params)' function defined in
+// 'SCRIPT' as '{"method": ..., "params": ...}' JSON strings. Responses are
+// either '{"result": ...}' or '{"error": {"code": ..., "message": ...}}'.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use lib::JavascriptCallback;
+
+// The error codes match the ones defined by the JSON-RPC 2.0 specification.
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+// Used for errors returned by handlers.
+pub const HANDLER_ERROR: i32 = -32000;
+
+// Defines the 'rpc' object. 'rpc.call' returns a promise which is rejected
+// with an 'Error' carrying a 'code' property when the request fails.
+// 'rpc.callSync' returns the result directly and throws on failure; use it
+// with engines which don't support promises.
+pub const SCRIPT: &str = r#"
+    var rpc = {
+        callSync: function(method, params) {
+            var request = JSON.stringify({
+                method: method,
+                params: params === undefined ? null : params
+            });
+            var response = JSON.parse(external.invoke(request));
+
+            if (response.error) {
+                var error = new Error(response.error.message);
+                error.code = response.error.code;
+                throw error;
+            }
+
+            return response.result;
+        },
+        call: function(method, params) {
+            return new Promise(function(resolve) {
+                resolve(rpc.callSync(method, params));
+            });
+        }
+    };
+"#;
+
+type Method = Box<dyn Fn(Value) -> Result<Value, (i32, String)>>;
+
+#[derive(Default)]
+pub struct Rpc {
+    methods: HashMap<String, Method>,
+}
+
+impl Rpc {
+    pub fn new() -> Rpc {
+        Rpc::default()
+    }
+
+    // Registers a handler for the specified method replacing the previous
+    // one. Parameters are deserialized from the 'params' value, so methods
+    // without parameters should use the '()' type.
+    pub fn register<P, R, E, F>(&mut self, method: &str, handler: F)
+        where P: DeserializeOwned,
+              R: Serialize,
+              E: Display,
+              F: Fn(P) -> Result<R, E> + 'static
+    {
+        let method_name = method.to_string();
+
+        self.methods.insert(
+            method.to_string(),
+            Box::new(move |params: Value| {
+                let params = serde_json::from_value(params)
+                    .map_err(|error| (
+                        INVALID_PARAMS,
+                        format!(
+                            "Invalid parameters of the '{}' method: {}",
+                            method_name,
+                            error)))?;
+
+                let result = handler(params)
+                    .map_err(|error| (HANDLER_ERROR, error.to_string()))?;
+
+                serde_json::to_value(result)
+                    .map_err(|error| (HANDLER_ERROR, error.to_string()))
+            }));
+    }
+
+    // Processes a request and returns a serialized response.
+    pub fn handle(&self, message: &str) -> String {
+        let response = match self.dispatch(message) {
+            Ok(result) => json!({"result": result}),
+            Err((code, message)) => json!({
+                "error": {"code": code, "message": message}
+            }),
+        };
+
+        response.to_string()
+    }
+
+    fn dispatch(&self, message: &str) -> Result<Value, (i32, String)> {
+        let mut request: Value = serde_json::from_str(message)
+            .map_err(|error| (PARSE_ERROR, error.to_string()))?;
+
+        let method_name = request
+            .get("method")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| (
+                INVALID_REQUEST,
+                "The request doesn't specify a method".to_string()))?;
+
+        let method = self.methods
+            .get(&method_name)
+            .ok_or_else(|| (
+                METHOD_NOT_FOUND,
+                format!("The '{}' method is not registered", method_name)))?;
+
+        method(request.get_mut("params").map(Value::take).unwrap_or(Value::Null))
+    }
+
+    pub fn into_callback(self) -> JavascriptCallback {
+        Box::new(move |message: String| self.handle(&message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rpc() -> Rpc {
+        let mut rpc = Rpc::new();
+
+        rpc.register("add", |(a, b): (i32, i32)| Ok::<i32, String>(a + b));
+        rpc.register("version", |()| Ok::<&str, String>("1.0"));
+        rpc.register("fail", |()| Err::<(), &str>("Something went wrong"));
+
+        rpc
+    }
+
+    fn error_code(result: Result<Value, (i32, String)>) -> i32 {
+        match result {
+            Ok(value) => panic!("Unexpected result {}", value),
+            Err((code, _)) => code,
+        }
+    }
+
+    #[test]
+    fn dispatch_calls_methods() {
+        let rpc = rpc();
+
+        assert_eq!(
+            rpc.dispatch(r#"{"method": "add", "params": [2, 3]}"#),
+            Ok(json!(5)));
+        assert_eq!(rpc.dispatch(r#"{"method": "version"}"#), Ok(json!("1.0")));
+        assert_eq!(
+            rpc.dispatch(r#"{"method": "version", "params": null}"#),
+            Ok(json!("1.0")));
+    }
+
+    #[test]
+    fn dispatch_reports_errors() {
+        let rpc = rpc();
+
+        assert_eq!(error_code(rpc.dispatch("{")), PARSE_ERROR);
+        assert_eq!(
+            error_code(rpc.dispatch(r#"{"params": []}"#)), INVALID_REQUEST);
+        assert_eq!(
+            error_code(rpc.dispatch(r#"{"method": "multiply"}"#)),
+            METHOD_NOT_FOUND);
+        assert_eq!(
+            error_code(rpc.dispatch(r#"{"method": "add", "params": "2"}"#)),
+            INVALID_PARAMS);
+        assert_eq!(
+            rpc.dispatch(r#"{"method": "fail"}"#),
+            Err((HANDLER_ERROR, "Something went wrong".to_string())));
+    }
+
+    #[test]
+    fn handle_serializes_responses() {
+        let rpc = rpc();
+
+        assert_eq!(
+            rpc.handle(r#"{"method": "add", "params": [1, 1]}"#),
+            r#"{"result":2}"#);
+        assert_eq!(
+            rpc.handle(r#"{"method": "fail"}"#),
+            json!({
+                "error": {
+                    "code": HANDLER_ERROR,
+                    "message": "Something went wrong",
+                }
+            }).to_string());
+    }
+}
@@ -0,0 +1,200 @@
+// Helpers for the scripts which the crate adds to HTML documents.
+
+// Checks whether the text starts with a tag with the specified name, so that
+// '<head' doesn't match '<header>'.
+fn starts_with_tag(text: &str, tag_name: &str) -> bool {
+    match text.strip_prefix(tag_name) {
+        Some(rest) => rest
+            .chars()
+            .next()
+            .is_none_or(|character|
+                character.is_ascii_whitespace() ||
+                character == '/' ||
+                character == '>'),
+        None => false,
+    }
+}
+
+// Finds the end of the first tag with the specified name in the lowercase
+// document.
+fn find_tag_end(lowercase_document: &str, tag_name: &str) -> Option<usize> {
+    let tag_start = lowercase_document
+        .match_indices(tag_name)
+        .map(|(tag_start, _)| tag_start)
+        .find(|&tag_start|
+            starts_with_tag(&lowercase_document[tag_start..], tag_name))?;
+
+    lowercase_document[tag_start..]
+        .find('>')
+        .map(|tag_end| tag_start + tag_end + 1)
+}
+
+// Skips the comments and the 'meta', 'title' and 'base' elements which
+// start the head. They must stay ahead of the inserted elements: Internet
+// Explorer ignores the 'X-UA-Compatible' meta element after scripts and
+// falls back to the IE7 mode.
+fn skip_metadata(lowercase_document: &str, mut position: usize) -> usize {
+    loop {
+        let rest = &lowercase_document[position..];
+        let element = rest.trim_start();
+        let element_start = position + rest.len() - element.len();
+
+        let element_end = if element.starts_with("<!--") {
+            element.find("-->").map(|end| end + 3)
+        } else if starts_with_tag(element, "<meta") ||
+                starts_with_tag(element, "<base") {
+            element.find('>').map(|end| end + 1)
+        } else if starts_with_tag(element, "<title") {
+            find_tag_end(element, "</title")
+        } else {
+            None
+        };
+
+        match element_end {
+            Some(element_end) => position = element_start + element_end,
+            None => return position,
+        }
+    }
+}
+
+fn insert_into_head(html_document: &str, html: &str) -> String {
+    // ASCII lowercasing preserves byte offsets.
+    let lowercase_document = html_document.to_ascii_lowercase();
+
+    let position = find_tag_end(&lowercase_document, "<head")
+        .or_else(|| find_tag_end(&lowercase_document, "<html"))
+        .or_else(|| find_tag_end(&lowercase_document, "<!doctype"))
+        .unwrap_or(0);
+    let position = skip_metadata(&lowercase_document, position);
+
+    format!(
+        "{}{}{}",
+        &html_document[..position],
+        html,
+        &html_document[position..])
+}
+
+// Inserts an inline script at the beginning of the document's head (after
+// the leading metadata) so that it runs before any scripts of the document.
+pub fn inject(html_document: &str, script: &str) -> String {
+    insert_into_head(html_document, &format!("<script>{}</script>", script))
+}
+
+// Makes relative URLs of the document refer to the specified URL. Documents
+// which specify a base URL themselves are left intact.
+#[cfg(windows)]
+pub fn set_base_url(html_document: &str, url: &str) -> String {
+    let lowercase_document = html_document.to_ascii_lowercase();
+
+    if find_tag_end(&lowercase_document, "<base").is_some() {
+        return html_document.to_string();
+    }
+
+    insert_into_head(html_document, &format!("<base href=\"{}\">", url))
+}
+
+// The scripts which are added to every document loaded into the window.
+pub struct Template {
+    // In the order of execution.
+    scripts: Vec<String>,
+}
+
+impl Template {
+    pub fn new() -> Template {
+        Template {
+            scripts: Vec::new(),
+        }
+    }
+
+    // The script runs after the previously added ones.
+    pub fn push<S: Into<String>>(&mut self, script: S) {
+        self.scripts.push(script.into());
+    }
+
+    // Returns the scripts as a single one for pages which are not loaded
+    // from documents, so the scripts can't be inserted into them.
+    pub fn script(&self) -> String {
+        self.scripts.join(";\n")
+    }
+
+    pub fn apply(&self, html_document: &str) -> String {
+        // Each script is inserted before the previous ones, so the last
+        // script is inserted first.
+        let mut html_document = html_document.to_string();
+
+        for script in self.scripts.iter().rev() {
+            html_document = inject(&html_document, script);
+        }
+
+        html_document
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inject_inserts_the_script_into_the_head() {
+        assert_eq!(
+            inject("<!DOCTYPE html><HTML><Head lang=\"en\"><p>\u{e9}", "run()"),
+            "<!DOCTYPE html><HTML><Head lang=\"en\"><script>run()</script>\
+            <p>\u{e9}");
+        assert_eq!(
+            inject("<header></header><head></head>", "run()"),
+            "<header></header><head><script>run()</script></head>");
+    }
+
+    #[test]
+    fn inject_keeps_the_metadata_first() {
+        assert_eq!(
+            inject(
+                "<head>\n  <META http-equiv=\"x-ua-compatible\" content=\"IE=edge\">\
+                \n  <!-- <script> --><title>\u{e9}</title><base href=\"/\"/>\
+                \n  <link rel=\"stylesheet\" href=\"main.css\"></head>",
+                "run()"),
+            "<head>\n  <META http-equiv=\"x-ua-compatible\" content=\"IE=edge\">\
+            \n  <!-- <script> --><title>\u{e9}</title><base href=\"/\"/>\
+            <script>run()</script>\
+            \n  <link rel=\"stylesheet\" href=\"main.css\"></head>");
+        assert_eq!(
+            inject("<html><meta charset=\"utf-8\"><metadata>", "run()"),
+            "<html><meta charset=\"utf-8\"><script>run()</script><metadata>");
+    }
+
+    #[test]
+    fn inject_handles_documents_without_a_head() {
+        assert_eq!(
+            inject("<html><body></body></html>", "run()"),
+            "<html><script>run()</script><body></body></html>");
+        assert_eq!(
+            inject("<!doctype html><p>Text", "run()"),
+            "<!doctype html><script>run()</script><p>Text");
+        assert_eq!(inject("Text", "run()"), "<script>run()</script>Text");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn set_base_url_keeps_the_documents_base_url() {
+        assert_eq!(
+            set_base_url("<head><title></title></head>", "plugin://app/"),
+            "<head><title></title><base href=\"plugin://app/\"></head>");
+        assert_eq!(
+            set_base_url("<head><base href=\"/\"></head>", "plugin://app/"),
+            "<head><base href=\"/\"></head>");
+        assert_eq!(
+            set_base_url("<head><basefont></head>", "plugin://app/"),
+            "<head><base href=\"plugin://app/\"><basefont></head>");
+    }
+
+    #[test]
+    fn template_runs_scripts_in_order() {
+        let mut template = Template::new();
+        template.push("first()");
+        template.push("second()");
+
+        assert_eq!(
+            template.apply("<head></head>"),
+            "<head><script>first()</script><script>second()</script></head>");
+    }
+}
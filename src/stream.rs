@@ -0,0 +1,338 @@
+// Streams samples (e.g. waveforms or peak levels) from the audio thread to the
+// page.
+//
+// The audio thread writes samples into a ring buffer using 'StreamWriter'.
+// Writing is wait-free: the writer never waits for the reader and simply
+// overwrites samples the reader didn't consume in time. The GUI collects new
+// samples on idle at most 'frame_rate' times per second and passes them as a
+// 'Float32Array' to the handlers registered in the page with
+// 'vst.onStream(name, function(samples) {...})'.
+
+use std::sync::atomic::{fence, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use events::to_javascript;
+use lib::PluginGui;
+
+pub const SCRIPT: &str = r#"
+    window.vst = window.vst || {};
+    (function() {
+        var handlers = {};
+
+        function decode(data) {
+            var characters = atob(data);
+            var bytes = new Uint8Array(characters.length);
+
+            for (var index = 0; index < characters.length; ++index) {
+                bytes[index] = characters.charCodeAt(index);
+            }
+
+            return new Float32Array(bytes.buffer);
+        }
+
+        vst.onStream = function(name, handler) {
+            (handlers[name] = handlers[name] || []).push(handler);
+        };
+        vst.receiveStream = function(name, data) {
+            var list = handlers[name] || [];
+            var samples = decode(data);
+
+            for (var index = 0; index < list.length; ++index) {
+                list[index](samples);
+            }
+        };
+    })();
+"#;
+
+struct RingBuffer {
+    // The bits of 'f32' values.
+    samples: Vec<AtomicU32>,
+    // The total number of samples written so far.
+    write_position: AtomicUsize,
+    // The position the writer writes up to. It's published before the
+    // samples are written, so readers can tell which samples the writer
+    // could overwrite while they were being read.
+    reserved_position: AtomicUsize,
+}
+
+pub struct StreamWriter {
+    buffer: Arc<RingBuffer>,
+    write_position: usize,
+}
+
+impl StreamWriter {
+    pub fn write(&mut self, samples: &[f32]) {
+        let capacity = self.buffer.samples.len();
+
+        // Only the last 'capacity' samples can be read anyway.
+        let samples = &samples[samples.len().saturating_sub(capacity)..];
+
+        self.buffer.reserved_position.store(
+            self.write_position + samples.len(), Ordering::Relaxed);
+        fence(Ordering::Release);
+
+        for sample in samples {
+            self.buffer.samples[self.write_position % capacity]
+                .store(sample.to_bits(), Ordering::Relaxed);
+            self.write_position += 1;
+        }
+
+        self.buffer.write_position.store(
+            self.write_position, Ordering::Release);
+    }
+
+    pub fn write_sample(&mut self, sample: f32) {
+        self.write(&[sample]);
+    }
+
+    // Writes the peak absolute value of the samples.
+    pub fn write_peak(&mut self, samples: &[f32]) {
+        let peak = samples
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+
+        self.write_sample(peak);
+    }
+}
+
+pub struct StreamReader {
+    buffer: Arc<RingBuffer>,
+    read_position: usize,
+}
+
+impl StreamReader {
+    // Returns samples written since the previous call. Samples overwritten
+    // before they were read are skipped.
+    fn read(&mut self) -> Vec<f32> {
+        let capacity = self.buffer.samples.len();
+        let write_position =
+            self.buffer.write_position.load(Ordering::Acquire);
+
+        let start = self.read_position.max(
+            write_position.saturating_sub(capacity));
+
+        let samples: Vec<u32> = (start..write_position)
+            .map(|position|
+                self.buffer.samples[position % capacity]
+                    .load(Ordering::Relaxed))
+            .collect();
+
+        // The writer could overwrite the oldest samples while we were
+        // reading them. If we saw any sample of a write, we see its
+        // reservation as well, so the samples which the write could
+        // overwrite are discarded even if it's not complete yet.
+        fence(Ordering::Acquire);
+        let valid_start = self.buffer.reserved_position
+            .load(Ordering::Relaxed)
+            .saturating_sub(capacity)
+            .max(start);
+
+        self.read_position = write_position;
+
+        samples[valid_start.min(write_position) - start..]
+            .iter()
+            .map(|&bits| f32::from_bits(bits))
+            .collect()
+    }
+}
+
+// Creates a stream which keeps at most 'capacity' unread samples.
+pub fn new_stream(capacity: usize) -> (StreamWriter, StreamReader) {
+    assert!(capacity > 0);
+
+    let buffer = Arc::new(
+        RingBuffer {
+            samples: (0..capacity).map(|_| AtomicU32::new(0)).collect(),
+            write_position: AtomicUsize::new(0),
+            reserved_position: AtomicUsize::new(0),
+        });
+
+    let writer = StreamWriter {
+        buffer: buffer.clone(),
+        write_position: 0,
+    };
+
+    let reader = StreamReader {
+        buffer,
+        read_position: 0,
+    };
+
+    (writer, reader)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let value = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (index, &byte)|
+                value | (byte as u32) << (16 - 8 * index));
+
+        for index in 0..4 {
+            if index <= chunk.len() {
+                let sextet = (value >> (18 - 6 * index)) & 0x3f;
+                result.push(ALPHABET[sextet as usize] as char);
+            } else {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
+
+pub struct Stream {
+    // The name as a Javascript string literal.
+    name: String,
+    reader: StreamReader,
+    frame_interval: Duration,
+    last_frame: Option<Instant>,
+}
+
+impl Stream {
+    pub fn new(name: &str, reader: StreamReader, frame_rate: f32) -> Stream {
+        assert!(frame_rate > 0.0);
+
+        Stream {
+            name: to_javascript(name).expect("Strings are always serializable"),
+            reader,
+            frame_interval: Duration::from_secs_f32(1.0 / frame_rate),
+            last_frame: None,
+        }
+    }
+
+    // Passes new samples to the page if it's time for the next frame. When
+    // the page is not ready the samples are discarded.
+    pub fn update(&mut self, gui: &dyn PluginGui, is_page_ready: bool) {
+        let now = Instant::now();
+
+        let is_frame_due = self.last_frame
+            .map(|last_frame| now - last_frame >= self.frame_interval)
+            .unwrap_or(true);
+
+        if !is_frame_due {
+            return;
+        }
+
+        self.last_frame = Some(now);
+
+        let samples = self.reader.read();
+
+        if samples.is_empty() || !is_page_ready {
+            return;
+        }
+
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_ne_bytes().to_vec())
+            .collect();
+
+        let javascript_code = format!(
+            "vst.receiveStream({}, \"{}\");",
+            self.name,
+            base64(&bytes));
+
+        // A lost frame is not worth reporting: the next one follows soon.
+        let _ = gui.execute(&javascript_code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn read_returns_new_samples() {
+        let (mut writer, mut reader) = new_stream(8);
+
+        assert!(reader.read().is_empty());
+
+        writer.write(&[1.0, 2.0]);
+        writer.write_sample(3.0);
+        assert_eq!(reader.read(), vec![1.0, 2.0, 3.0]);
+        assert!(reader.read().is_empty());
+
+        writer.write_peak(&[0.5, -4.0, 2.0]);
+        assert_eq!(reader.read(), vec![4.0]);
+    }
+
+    #[test]
+    fn read_wraps_around() {
+        let (mut writer, mut reader) = new_stream(4);
+
+        writer.write(&[1.0, 2.0, 3.0]);
+        assert_eq!(reader.read(), vec![1.0, 2.0, 3.0]);
+
+        writer.write(&[4.0, 5.0, 6.0]);
+        assert_eq!(reader.read(), vec![4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn read_skips_overwritten_samples() {
+        let (mut writer, mut reader) = new_stream(4);
+
+        writer.write(&[1.0, 2.0, 3.0]);
+        writer.write(&[4.0, 5.0, 6.0]);
+        assert_eq!(reader.read(), vec![3.0, 4.0, 5.0, 6.0]);
+
+        writer.write(&[7.0, 8.0, 9.0, 10.0, 11.0, 12.0]);
+        assert_eq!(reader.read(), vec![9.0, 10.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn read_never_returns_overwritten_samples() {
+        const CAPACITY: usize = 16;
+        const COUNT: u32 = 200_000;
+
+        let (mut writer, mut reader) = new_stream(CAPACITY);
+
+        let writing = thread::spawn(move || {
+            let mut samples = [0.0; 5];
+            let mut next = 1;
+
+            while next <= COUNT {
+                for sample in samples.iter_mut() {
+                    *sample = next as f32;
+                    next += 1;
+                }
+                writer.write(&samples);
+            }
+        });
+
+        // Samples are consecutive numbers, so each batch which is read must
+        // be consecutive and follow the previous batches.
+        let mut last = 0.0;
+
+        while last < COUNT as f32 {
+            let samples = reader.read();
+
+            for (index, &sample) in samples.iter().enumerate() {
+                assert!(sample > last);
+                assert!(index == 0 || sample == samples[index - 1] + 1.0);
+                last = sample;
+            }
+        }
+
+        writing.join().unwrap();
+    }
+
+    #[test]
+    fn base64_encodes_bytes() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xef, 0x00]), "/+8A");
+    }
+}
@@ -0,0 +1,914 @@
+// A library of custom elements for audio controls:
+//
+//     <vst-knob param="0" default="0.5"></vst-knob>
+//     <vst-fader param="1" orientation="horizontal"></vst-fader>
+//     <vst-xy-pad param-x="2" param-y="3"></vst-xy-pad>
+//     <vst-meter stream="level" min-db="-48"></vst-meter>
+//     <vst-keyboard start="48" octaves="2" handler="notes"></vst-keyboard>
+//
+// Knobs, faders and XY pads change the parameters with the given indices
+// through the parameter binding (see 'PluginGuiBuilder::parameters'). They
+// are dragged (hold Shift for fine tuning) or scrolled, reset to their
+// 'default' values (0 by default) on double click and display the
+// parameters' text. Pages without the binding receive 'change' events with
+// the values in 'event.detail.value' instead.
+//
+// Meters display the peak level of a stream (see 'vst.onStream') or the
+// value of a parameter set with 'param'. Keyboards dispatch 'noteon' and
+// 'noteoff' events with 'event.detail.note' and 'event.detail.velocity' and
+// pass the notes to the 'external.<handler>' handler as "on <note>
+// <velocity>" and "off <note>" messages (see 'PluginGuiBuilder::handler').
+//
+// The library is inlined into the document with 'PluginGuiBuilder::widgets'
+// or served as assets after 'add_to(&mut assets)':
+//
+//     <link rel="stylesheet" href="vst-widgets.css">
+//     <script src="vst-widgets.js"></script>
+//
+// Attributes are read once when the elements are attached to the document.
+// Browsers without custom elements (Internet Explorer) get the elements
+// upgraded when the document is loaded and when they are added to it.
+
+use serde_json::Value;
+
+use assets::Assets;
+
+pub const SCRIPT_PATH: &str = "vst-widgets.js";
+pub const STYLE_PATH: &str = "vst-widgets.css";
+
+pub const SCRIPT: &str = r#"
+window.vst = window.vst || {};
+(function() {
+    // The distance in pixels which drags a control through the whole range.
+    var DRAG_DISTANCE = 200;
+    // Applied to the drag distance and the scroll step while Shift is held.
+    var FINE_SCALE = 0.1;
+    var SCROLL_STEP = 0.02;
+    // The meter level is multiplied by this value on every frame.
+    var METER_DECAY = 0.85;
+    var BLACK_KEYS = [1, 3, 6, 8, 10];
+
+    function clamp(value) {
+        return Math.min(1, Math.max(0, value));
+    }
+
+    function number(element, name, fallback) {
+        var value = parseFloat(element.getAttribute(name));
+        return isNaN(value) ? fallback : value;
+    }
+
+    function create(parent, className) {
+        var element = document.createElement("div");
+        element.className = className;
+        parent.appendChild(element);
+        return element;
+    }
+
+    function fire(element, type, detail) {
+        var event = document.createEvent("CustomEvent");
+        event.initCustomEvent(type, true, false, detail);
+        element.dispatchEvent(event);
+    }
+
+    // The parameters displayed by the widgets. The listener is added once
+    // the binding is available.
+    var parameters = [];
+    var listening = false;
+
+    function listen() {
+        if (listening || !vst.parameters) {
+            return;
+        }
+
+        vst.parameters.addListener(function(index, value) {
+            for (var i = 0; i < parameters.length; ++i) {
+                if (parameters[i].index === index) {
+                    parameters[i].value = value;
+                    parameters[i].render();
+                }
+            }
+        });
+
+        listening = true;
+    }
+
+    function Parameter(element, attribute, render) {
+        listen();
+
+        this.element = element;
+        this.index = parseInt(element.getAttribute(attribute), 10);
+        this.bound = !!vst.parameters && !isNaN(this.index);
+        this.value = this.bound ? vst.parameters.get(this.index) : 0;
+        this.render = render;
+
+        parameters.push(this);
+    }
+
+    Parameter.prototype.begin = function() {
+        if (this.bound) {
+            vst.beginEdit(this.index);
+        }
+    };
+
+    Parameter.prototype.change = function(value) {
+        this.value = clamp(value);
+
+        if (this.bound) {
+            vst.setParameter(this.index, this.value);
+        }
+
+        this.render();
+        fire(this.element, "change", {index: this.index, value: this.value});
+    };
+
+    Parameter.prototype.end = function() {
+        if (this.bound) {
+            vst.endEdit(this.index);
+        }
+    };
+
+    // Changes the value as a single gesture.
+    Parameter.prototype.edit = function(value) {
+        this.begin();
+        this.change(value);
+        this.end();
+    };
+
+    Parameter.prototype.text = function() {
+        if (!this.bound) {
+            return this.value.toFixed(2);
+        }
+
+        var text = vst.parameters.getText(this.index);
+        var label = vst.parameters.getLabel(this.index);
+
+        return label ? text + " " + label : text;
+    };
+
+    // Calls 'move(dx, dy)' with the pointer movements until the button is
+    // released. The movements are scaled down while Shift is held.
+    function drag(target, handlers) {
+        target.addEventListener("mousedown", function(event) {
+            if (event.button !== 0) {
+                return;
+            }
+
+            var x = event.clientX;
+            var y = event.clientY;
+
+            function move(event) {
+                var scale = event.shiftKey ? FINE_SCALE : 1;
+
+                handlers.move(
+                    (event.clientX - x) * scale, (event.clientY - y) * scale);
+
+                x = event.clientX;
+                y = event.clientY;
+            }
+
+            function release() {
+                document.removeEventListener("mousemove", move);
+                document.removeEventListener("mouseup", release);
+                handlers.end();
+            }
+
+            handlers.start(event);
+            document.addEventListener("mousemove", move);
+            document.addEventListener("mouseup", release);
+            event.preventDefault();
+        });
+    }
+
+    function scroll(target, parameter) {
+        target.addEventListener("wheel", function(event) {
+            var step = SCROLL_STEP * (event.shiftKey ? FINE_SCALE : 1);
+
+            parameter.edit(
+                parameter.value + (event.deltaY < 0 ? step : -step));
+            event.preventDefault();
+        });
+    }
+
+    function reset(target, parameter, attribute) {
+        target.addEventListener("dblclick", function() {
+            parameter.edit(number(parameter.element, attribute, 0));
+        });
+    }
+
+    function setupKnob(element) {
+        var dial = create(element, "vst-knob-dial");
+        var indicator = create(dial, "vst-knob-indicator");
+        var text = create(element, "vst-value");
+
+        var parameter = new Parameter(element, "param", function() {
+            indicator.style.transform =
+                "rotate(" + (270 * parameter.value - 135) + "deg)";
+            text.textContent = parameter.text();
+        });
+
+        drag(dial, {
+            start: function() {
+                parameter.begin();
+            },
+            move: function(dx, dy) {
+                parameter.change(parameter.value - dy / DRAG_DISTANCE);
+            },
+            end: function() {
+                parameter.end();
+            }
+        });
+
+        scroll(dial, parameter);
+        reset(dial, parameter, "default");
+        parameter.render();
+    }
+
+    function setupFader(element) {
+        var vertical = element.getAttribute("orientation") !== "horizontal";
+        var track = create(element, "vst-fader-track");
+        var fill = create(track, "vst-fader-fill");
+        var thumb = create(track, "vst-fader-thumb");
+        var text = create(element, "vst-value");
+
+        if (!vertical) {
+            element.className += " vst-fader-horizontal";
+        }
+
+        var parameter = new Parameter(element, "param", function() {
+            var position = parameter.value * 100 + "%";
+
+            if (vertical) {
+                fill.style.height = position;
+                thumb.style.bottom = position;
+            } else {
+                fill.style.width = position;
+                thumb.style.left = position;
+            }
+
+            text.textContent = parameter.text();
+        });
+
+        drag(track, {
+            start: function() {
+                parameter.begin();
+            },
+            move: function(dx, dy) {
+                var length = vertical ?
+                    track.offsetHeight : track.offsetWidth;
+                var delta = vertical ? -dy : dx;
+
+                parameter.change(
+                    parameter.value + delta / (length || DRAG_DISTANCE));
+            },
+            end: function() {
+                parameter.end();
+            }
+        });
+
+        scroll(track, parameter);
+        reset(track, parameter, "default");
+        parameter.render();
+    }
+
+    function setupXyPad(element) {
+        var area = create(element, "vst-xy-pad-area");
+        var handle = create(area, "vst-xy-pad-handle");
+        var text = create(element, "vst-value");
+
+        function render() {
+            handle.style.left = x.value * 100 + "%";
+            handle.style.bottom = y.value * 100 + "%";
+            text.textContent = x.text() + " / " + y.text();
+        }
+
+        var x = new Parameter(element, "param-x", render);
+        var y = new Parameter(element, "param-y", render);
+
+        // A click moves the handle to the pointer unless Shift is held.
+        drag(area, {
+            start: function(event) {
+                x.begin();
+                y.begin();
+
+                if (!event.shiftKey) {
+                    var rectangle = area.getBoundingClientRect();
+
+                    x.change((event.clientX - rectangle.left) /
+                        rectangle.width);
+                    y.change(1 - (event.clientY - rectangle.top) /
+                        rectangle.height);
+                }
+            },
+            move: function(dx, dy) {
+                x.change(x.value + dx / (area.offsetWidth || DRAG_DISTANCE));
+                y.change(y.value - dy / (area.offsetHeight || DRAG_DISTANCE));
+            },
+            end: function() {
+                x.end();
+                y.end();
+            }
+        });
+
+        scroll(area, y);
+
+        area.addEventListener("dblclick", function() {
+            x.edit(number(element, "default-x", 0));
+            y.edit(number(element, "default-y", 0));
+        });
+
+        render();
+    }
+
+    function setupMeter(element) {
+        var vertical = element.getAttribute("orientation") !== "horizontal";
+        var track = create(element, "vst-meter-track");
+        var bar = create(track, "vst-meter-bar");
+        var text = create(element, "vst-value");
+        var minimum = number(element, "min-db", -60);
+        var stream = element.getAttribute("stream");
+        var level = 0;
+
+        if (!vertical) {
+            element.className += " vst-meter-horizontal";
+        }
+
+        function show(position, label) {
+            bar.style[vertical ? "height" : "width"] =
+                clamp(position) * 100 + "%";
+            text.textContent = label;
+        }
+
+        if (stream) {
+            vst.onStream(stream, function(samples) {
+                var peak = 0;
+
+                for (var i = 0; i < samples.length; ++i) {
+                    peak = Math.max(peak, Math.abs(samples[i]));
+                }
+
+                level = Math.max(peak, level * METER_DECAY);
+
+                var decibels = 20 * Math.log(level) / Math.LN10;
+
+                if (decibels > minimum) {
+                    show(1 - decibels / minimum, decibels.toFixed(1) + " dB");
+                } else {
+                    show(0, "-inf dB");
+                }
+            });
+
+            show(0, "-inf dB");
+            return;
+        }
+
+        var parameter = new Parameter(element, "param", function() {
+            show(parameter.value, parameter.text());
+        });
+
+        parameter.render();
+    }
+
+    function setupKeyboard(element) {
+        var start = Math.round(number(element, "start", 48));
+        var octaves = Math.max(1, Math.round(number(element, "octaves", 2)));
+        var velocity = Math.round(number(element, "velocity", 100));
+        var handler = element.getAttribute("handler");
+        var keys = {};
+        var pressed = null;
+
+        function send(on, note) {
+            var message = on ? "on " + note + " " + velocity : "off " + note;
+
+            keys[note].className =
+                keys[note].baseClassName + (on ? " vst-key-pressed" : "");
+
+            fire(element, on ? "noteon" : "noteoff", {
+                note: note,
+                velocity: on ? velocity : 0
+            });
+
+            if (handler && window.external && external[handler]) {
+                external[handler](message);
+            }
+        }
+
+        function press(note) {
+            if (pressed !== null) {
+                send(false, pressed);
+            }
+
+            pressed = note;
+
+            if (note !== null) {
+                send(true, note);
+            }
+        }
+
+        var end = start + 12 * octaves;
+        var whiteCount = 0;
+
+        for (var note = start; note <= end; ++note) {
+            if (BLACK_KEYS.indexOf(note % 12) === -1) {
+                ++whiteCount;
+            }
+        }
+
+        var width = 100 / whiteCount;
+        var whiteIndex = 0;
+
+        for (note = start; note <= end; ++note) {
+            var black = BLACK_KEYS.indexOf(note % 12) !== -1;
+            var key = create(
+                element, black ? "vst-key vst-key-black" : "vst-key");
+
+            key.baseClassName = key.className;
+
+            if (black) {
+                key.style.left = (whiteIndex - 0.3) * width + "%";
+                key.style.width = 0.6 * width + "%";
+            } else {
+                key.style.left = whiteIndex * width + "%";
+                key.style.width = width + "%";
+                ++whiteIndex;
+            }
+
+            keys[note] = key;
+
+            (function(note) {
+                key.addEventListener("mousedown", function(event) {
+                    if (event.button === 0) {
+                        press(note);
+                        event.preventDefault();
+                    }
+                });
+                // Sliding over the keys plays them in turn.
+                key.addEventListener("mouseenter", function() {
+                    if (pressed !== null) {
+                        press(note);
+                    }
+                });
+            })(note);
+        }
+
+        document.addEventListener("mouseup", function() {
+            press(null);
+        });
+    }
+
+    var widgets = {
+        "vst-knob": setupKnob,
+        "vst-fader": setupFader,
+        "vst-xy-pad": setupXyPad,
+        "vst-meter": setupMeter,
+        "vst-keyboard": setupKeyboard
+    };
+
+    function upgrade(element) {
+        if (element.vstWidget) {
+            return;
+        }
+
+        element.vstWidget = true;
+        widgets[element.tagName.toLowerCase()](element);
+    }
+
+    function upgradeAll() {
+        for (var name in widgets) {
+            var elements = document.getElementsByTagName(name);
+
+            for (var i = 0; i < elements.length; ++i) {
+                upgrade(elements[i]);
+            }
+        }
+    }
+
+    if (window.customElements && window.Reflect) {
+        Object.keys(widgets).forEach(function(name) {
+            // Custom elements must be constructed like classes.
+            function Widget() {
+                return Reflect.construct(HTMLElement, [], Widget);
+            }
+
+            Widget.prototype = Object.create(HTMLElement.prototype);
+            Widget.prototype.constructor = Widget;
+            Widget.prototype.connectedCallback = function() {
+                upgrade(this);
+            };
+            Object.setPrototypeOf(Widget, HTMLElement);
+
+            if (!customElements.get(name)) {
+                customElements.define(name, Widget);
+            }
+        });
+    } else {
+        if (document.readyState === "loading") {
+            document.addEventListener("DOMContentLoaded", upgradeAll);
+        } else {
+            upgradeAll();
+        }
+
+        if (window.MutationObserver) {
+            new MutationObserver(upgradeAll).observe(
+                document, {childList: true, subtree: true});
+        }
+    }
+
+    vst.widgets = {
+        upgrade: upgradeAll
+    };
+})();
+"#;
+
+pub const STYLE: &str = r#"
+vst-knob, vst-fader, vst-xy-pad, vst-meter, vst-keyboard {
+    display: inline-block;
+    position: relative;
+    vertical-align: top;
+    color: #dddddd;
+    font-family: sans-serif;
+    font-size: 11px;
+    text-align: center;
+    -ms-user-select: none;
+    -webkit-user-select: none;
+    user-select: none;
+}
+.vst-value {
+    margin-top: 4px;
+    overflow: hidden;
+    color: #999999;
+    text-overflow: ellipsis;
+    white-space: nowrap;
+}
+vst-knob {
+    width: 64px;
+}
+.vst-knob-dial {
+    position: relative;
+    width: 48px;
+    height: 48px;
+    margin: 0 auto;
+    border: 2px solid #666666;
+    border-radius: 50%;
+    background: #444444;
+    cursor: ns-resize;
+}
+.vst-knob-indicator {
+    position: absolute;
+    left: 50%;
+    top: 4px;
+    width: 2px;
+    height: 18px;
+    margin-left: -1px;
+    background: #4aa3ff;
+    transform-origin: 50% 20px;
+}
+vst-fader {
+    width: 48px;
+}
+vst-fader.vst-fader-horizontal {
+    width: 160px;
+}
+.vst-fader-track {
+    position: relative;
+    width: 8px;
+    height: 120px;
+    margin: 8px auto;
+    border-radius: 4px;
+    background: #444444;
+    cursor: ns-resize;
+}
+.vst-fader-horizontal .vst-fader-track {
+    width: auto;
+    height: 8px;
+    margin: 8px;
+    cursor: ew-resize;
+}
+.vst-fader-fill {
+    position: absolute;
+    left: 0;
+    bottom: 0;
+    width: 100%;
+    border-radius: 4px;
+    background: #4aa3ff;
+}
+.vst-fader-horizontal .vst-fader-fill {
+    height: 100%;
+    width: 0;
+}
+.vst-fader-thumb {
+    position: absolute;
+    left: 50%;
+    width: 24px;
+    height: 12px;
+    margin: 0 0 -6px -12px;
+    border-radius: 3px;
+    background: #dddddd;
+}
+.vst-fader-horizontal .vst-fader-thumb {
+    top: 50%;
+    width: 12px;
+    height: 24px;
+    margin: -12px 0 0 -6px;
+}
+vst-xy-pad {
+    width: 160px;
+}
+.vst-xy-pad-area {
+    position: relative;
+    height: 160px;
+    border: 1px solid #666666;
+    background: #333333;
+    cursor: crosshair;
+}
+.vst-xy-pad-handle {
+    position: absolute;
+    width: 12px;
+    height: 12px;
+    margin: 0 0 -6px -6px;
+    border-radius: 50%;
+    background: #4aa3ff;
+}
+vst-meter {
+    width: 48px;
+}
+vst-meter.vst-meter-horizontal {
+    width: 160px;
+}
+.vst-meter-track {
+    position: relative;
+    width: 8px;
+    height: 120px;
+    margin: 8px auto;
+    background: #333333;
+}
+.vst-meter-horizontal .vst-meter-track {
+    width: auto;
+    height: 8px;
+    margin: 8px;
+}
+.vst-meter-bar {
+    position: absolute;
+    left: 0;
+    bottom: 0;
+    width: 100%;
+    background: #5fd35f;
+}
+.vst-meter-horizontal .vst-meter-bar {
+    height: 100%;
+    width: 0;
+}
+vst-keyboard {
+    width: 320px;
+    height: 80px;
+}
+.vst-key {
+    position: absolute;
+    top: 0;
+    height: 100%;
+    box-sizing: border-box;
+    border: 1px solid #666666;
+    background: #eeeeee;
+}
+.vst-key-black {
+    z-index: 1;
+    height: 60%;
+    background: #222222;
+}
+.vst-key.vst-key-pressed {
+    background: #4aa3ff;
+}
+"#;
+
+// Serves the library as 'vst-widgets.js' and 'vst-widgets.css'.
+pub fn add_to(assets: &mut Assets) {
+    assets.insert(SCRIPT_PATH, SCRIPT.as_bytes(), "text/javascript");
+    assets.insert(STYLE_PATH, STYLE.as_bytes(), "text/css");
+}
+
+// Adds the style sheet to the document, which may be empty when the script
+// starts.
+const STYLE_LOADER: &str = r#"
+(function(css) {
+    function addStyle() {
+        var style = document.createElement("style");
+        style.type = "text/css";
+        style.appendChild(document.createTextNode(css));
+        (document.head || document.documentElement).appendChild(style);
+    }
+
+    if (document.documentElement) {
+        addStyle();
+    } else {
+        document.addEventListener("DOMContentLoaded", addStyle);
+    }
+})"#;
+
+// The library along with its style sheet.
+pub fn inline_script() -> String {
+    format!("{}\n{}({});\n", SCRIPT, STYLE_LOADER, Value::from(STYLE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assets::AssetProvider;
+
+    #[test]
+    fn add_to_serves_the_library() {
+        let mut assets = Assets::new();
+        add_to(&mut assets);
+
+        let script = assets.get("vst-widgets.js").unwrap();
+        assert_eq!(script.content, SCRIPT.as_bytes());
+        assert_eq!(script.mime_type, "text/javascript");
+
+        let style = assets.get("vst-widgets.css").unwrap();
+        assert_eq!(style.content, STYLE.as_bytes());
+        assert_eq!(style.mime_type, "text/css");
+    }
+
+    #[test]
+    fn inline_script_includes_the_style_sheet() {
+        let script = inline_script();
+
+        assert!(script.starts_with(SCRIPT));
+        assert!(script.ends_with(&format!("({});\n", Value::from(STYLE))));
+    }
+
+    #[cfg(feature = "headless")]
+    mod page {
+        use std::cell::RefCell;
+        use std::ptr::null_mut;
+        use std::rc::Rc;
+        use std::sync::{Arc, Mutex};
+
+        use serde_json::json;
+        use vst::editor::Editor;
+        use vst::plugin::PluginParameters;
+
+        use super::super::STYLE;
+        use {PluginGui, PluginGuiBuilder};
+
+        // Dispatches mouse events like a browser would.
+        const MOUSE_SCRIPT: &str = "
+            function mouse(target, type, x, y, deltaY) {
+                var event = new Event(type, {bubbles: true});
+                event.button = 0;
+                event.clientX = x;
+                event.clientY = y;
+                event.deltaY = deltaY;
+                target.dispatchEvent(event);
+            }
+        ";
+
+        struct Parameters {
+            values: Mutex<Vec<f32>>,
+        }
+
+        impl PluginParameters for Parameters {
+            fn get_parameter(&self, index: i32) -> f32 {
+                self.values.lock().unwrap()[index as usize]
+            }
+
+            fn set_parameter(&self, index: i32, value: f32) {
+                self.values.lock().unwrap()[index as usize] = value;
+            }
+
+            fn get_parameter_text(&self, index: i32) -> String {
+                format!("{:.2}", self.get_parameter(index))
+            }
+
+            fn get_parameter_label(&self, _: i32) -> String {
+                "dB".to_string()
+            }
+        }
+
+        fn open_page(
+            html_document: &str,
+            parameters: Option<Arc<Parameters>>)
+            -> (PluginGui, Rc<RefCell<Vec<String>>>)
+        {
+            let notes = Rc::new(RefCell::new(Vec::new()));
+            let handler_notes = notes.clone();
+
+            let builder = PluginGuiBuilder::new()
+                .html(html_document)
+                .widgets()
+                .script(MOUSE_SCRIPT)
+                .handler("notes", Box::new(move |message| {
+                    handler_notes.borrow_mut().push(message);
+                    String::new()
+                }))
+                .headless();
+
+            let builder = match parameters {
+                Some(parameters) => builder.parameters(parameters, 2),
+                None => builder,
+            };
+
+            let mut gui = builder.build().unwrap();
+
+            assert!(gui.open(null_mut()));
+            gui.idle();
+
+            (gui, notes)
+        }
+
+        #[test]
+        fn style_sheet_is_added_to_the_head() {
+            let (gui, _) = open_page("", None);
+
+            assert_eq!(
+                gui.evaluate(
+                    "document.head.getElementsByTagName('style')\
+                    .map(function(style) { return style.childNodes[0].data; })")
+                    .unwrap(),
+                json!([STYLE]));
+        }
+
+        #[test]
+        fn knob_edits_the_parameter() {
+            let parameters = Arc::new(Parameters {
+                values: Mutex::new(vec![0.0, 0.25]),
+            });
+            let (gui, _) = open_page(
+                "<vst-knob id=\"knob\" param=\"1\" default=\"0.1\"></vst-knob>",
+                Some(parameters.clone()));
+
+            let state = || gui.evaluate(
+                "(function(knob) {\
+                    return [\
+                        knob.children.map(function(child) {\
+                            return child.className;\
+                        }),\
+                        knob.children[0].children[0].style.transform,\
+                        knob.children[1].textContent\
+                    ];\
+                })(document.getElementById('knob'))").unwrap();
+
+            assert_eq!(
+                state(),
+                json!([
+                    ["vst-knob-dial", "vst-value"],
+                    "rotate(-67.5deg)",
+                    "0.25 dB",
+                ]));
+
+            // Dragging the dial up by half of the drag distance.
+            gui.execute(
+                "var dial = document.getElementById('knob').children[0];\
+                mouse(dial, 'mousedown', 0, 100);\
+                mouse(document, 'mousemove', 0, 50);\
+                mouse(document, 'mouseup', 0, 50);").unwrap();
+
+            assert_eq!(parameters.get_parameter(1), 0.5);
+            assert_eq!(state()[1], "rotate(0deg)");
+            assert_eq!(state()[2], "0.50 dB");
+
+            gui.execute("dial.dispatchEvent(new Event('dblclick'));").unwrap();
+            assert!((parameters.get_parameter(1) - 0.1).abs() < 1e-6);
+        }
+
+        #[test]
+        fn unbound_fader_dispatches_change_events() {
+            let (gui, _) = open_page(
+                "<vst-fader id=\"fader\" orientation=\"horizontal\">\
+                </vst-fader>",
+                None);
+
+            gui.execute(
+                "var changes = [];\
+                document.addEventListener('change', function(event) {\
+                    changes.push(event.detail.value);\
+                });\
+                var fader = document.getElementById('fader');\
+                mouse(fader.children[0], 'wheel', 0, 0, -1);\
+                mouse(fader.children[0], 'wheel', 0, 0, -1);").unwrap();
+
+            assert_eq!(
+                gui.evaluate(
+                    "[fader.className.trim(), changes,\
+                    fader.children[1].textContent]").unwrap(),
+                json!(["vst-fader-horizontal", [0.02, 0.04], "0.04"]));
+        }
+
+        #[test]
+        fn keyboard_passes_notes_to_the_handler() {
+            let (gui, notes) = open_page(
+                "<vst-keyboard id=\"keys\" start=\"60\" octaves=\"1\" \
+                handler=\"notes\"></vst-keyboard>",
+                None);
+
+            gui.execute(
+                "var keys = document.getElementById('keys').children;\
+                mouse(keys[0], 'mousedown', 0, 0);\
+                keys[2].dispatchEvent(new Event('mouseenter'));\
+                mouse(document, 'mouseup', 0, 0);").unwrap();
+
+            assert_eq!(gui.evaluate("keys.length").unwrap(), json!(13));
+            assert_eq!(
+                *notes.borrow(),
+                vec!["on 60 100", "off 60", "on 62 100", "off 62"]);
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use std::boxed::Box;
+use std::cell::RefCell;
+use std::ffi::{OsStr, OsString};
+use std::iter::once;
+use std::os::windows::ffi::{OsStrExt, OsStringExt};
 use std::ptr::null_mut;
 use std::rc::Rc;
+use std::slice;
 
 // Non-asterisk imports are required to eliminate ambiguity
 use winapi::Interface;
@@ -13,19 +18,22 @@ use winapi::shared::windef::*;
 use winapi::shared::windef::SIZE;
 use winapi::shared::winerror::*;
 use winapi::shared::wtypesbase::*;
-use winapi::shared::wtypes::VT_BSTR;
+use winapi::shared::wtypes::{VT_BSTR, VT_DISPATCH};
 use winapi::um::oaidl::*;
 use winapi::um::oaidl::DISPID;
 use winapi::um::objidl::IMoniker;
 use winapi::um::oleauto::*;
 use winapi::um::unknwnbase::*;
 use winapi::um::winbase::*;
-use winapi::um::winnt::LCID;
+use winapi::um::winnt::{LCID, LOCALE_USER_DEFAULT};
 use winapi::um::winuser::*;
 
+use handlers;
+use keyboard::Keyboard;
 use lib::JavascriptCallback;
 use win32::com_pointer::ComPointer;
 use win32::ffi::*;
+use win32::keyboard;
 
 #[repr(C, packed)]
 struct ClientSite {
@@ -38,8 +46,28 @@ struct ClientSite {
     reference_counter: ULONG,
     window: HWND,
     callback: Rc<JavascriptCallback>,
+    // Owned by the client site. Only the pointer is kept in the packed
+    // structure since its fields can't be borrowed there.
+    state: *mut State,
 }
 
+struct State {
+    // The names of the methods of the 'external' object besides 'invoke'.
+    handlers: Vec<String>,
+    // The same names as null-terminated UTF-16 strings for
+    // 'IDispatch::GetIDsOfNames'.
+    wide_handlers: Vec<Vec<u16>>,
+    keyboard: Rc<Keyboard>,
+    // The 'vst.invokeAsync' function which the page assigns to
+    // 'external.invokeAsync'.
+    invoke_async: RefCell<ComPointer<IDispatch>>,
+}
+
+// The IDs of the 'invoke' method and the 'invokeAsync' property. Named
+// handlers have the following IDs.
+const INVOKE_ID: DISPID = 1;
+const INVOKE_ASYNC_ID: DISPID = 2;
+
 impl ClientSite {
     fn from_ole_in_place_site(
         instance: *mut IOleInPlaceSite) -> *mut ClientSite
@@ -62,6 +90,11 @@ impl ClientSite {
             instance as *mut u8, offset_of!(ClientSite, dispatch))
     }
 
+    unsafe fn state<'a>(client_site: *mut ClientSite) -> &'a State {
+        let state = (*client_site).state;
+        &*state
+    }
+
     fn from_member_and_offset(
         member: *mut u8, offset: usize) -> *mut ClientSite
     {
@@ -146,7 +179,9 @@ const DISPATCH_VTABLE: IDispatchVtbl = IDispatchVtbl {
 pub fn new_client_site(
     window: HWND,
     ole_in_place_object: ComPointer<IOleInPlaceObject>,
-    callback: Rc<JavascriptCallback>) -> ComPointer<IOleClientSite>
+    callback: Rc<JavascriptCallback>,
+    handlers: Vec<String>,
+    keyboard: Rc<Keyboard>) -> ComPointer<IOleClientSite>
 {
     let client_site = Box::new(
         ClientSite {
@@ -163,10 +198,22 @@ pub fn new_client_site(
                 lpVtbl: &DISPATCH_VTABLE,
             },
             ole_in_place_frame: new_in_place_frame(window),
-            ole_in_place_object: ole_in_place_object,
+            ole_in_place_object,
             reference_counter: 1,
-            window: window,
-            callback: callback,
+            window,
+            callback,
+            state: Box::into_raw(Box::new(
+                State {
+                    wide_handlers: handlers
+                        .iter()
+                        .map(|name|
+                            OsStr::new(name).encode_wide().chain(once(0))
+                                .collect())
+                        .collect(),
+                    handlers,
+                    keyboard,
+                    invoke_async: RefCell::new(ComPointer::new()),
+                })),
         });
 
     ComPointer::from_raw(Box::into_raw(client_site) as *mut IOleClientSite)
@@ -194,7 +241,8 @@ unsafe extern "system" fn IOleClientSite_Release(
     assert!(result != ULONG::max_value());
 
     if result == 0 {
-        Box::from_raw(client_site);
+        let client_site = Box::from_raw(client_site);
+        drop(Box::from_raw(client_site.state));
     }
 
     result
@@ -543,13 +591,25 @@ unsafe extern "system" fn IDocHostUIHandler_ResizeBorder(
     S_OK
 }
 
+// Keys which the page doesn't consume are forwarded to the host instead of
+// the browser.
 unsafe extern "system" fn IDocHostUIHandler_TranslateAccelerator(
-    _instance: *mut IDocHostUIHandler,
-    _lpMsg: LPMSG,
+    instance: *mut IDocHostUIHandler,
+    lpMsg: LPMSG,
     _pguidCmdGroup: *const GUID,
     _nCmdID: DWORD) -> HRESULT
 {
-    S_FALSE
+    let client_site = ClientSite::from_doc_host_ui_handler(instance);
+
+    let forwarded = keyboard::forward(
+        &ClientSite::state(client_site).keyboard,
+        (*client_site).window,
+        &*lpMsg);
+
+    match forwarded {
+        true => S_OK,
+        false => S_FALSE,
+    }
 }
 
 unsafe extern "system" fn IDocHostUIHandler_GetOptionKeyPath(
@@ -647,7 +707,7 @@ unsafe extern "system" fn IDispatch_GetTypeInfo(
 }
 
 unsafe extern "system" fn IDispatch_GetIDsOfNames(
-    _instance: *mut IDispatch,
+    instance: *mut IDispatch,
     _riid: REFIID,
     rgszNames: *mut LPOLESTR,
     cNames: UINT,
@@ -658,15 +718,104 @@ unsafe extern "system" fn IDispatch_GetIDsOfNames(
         *rgDispId.offset(index as isize) = DISPID_UNKNOWN;
     }
 
+    if cNames != 1 {
+        return DISP_E_UNKNOWNNAME;
+    }
+
     // The "invoke" string encoded in utf16.
     const METHOD_NAME: [u16; 7] = [105, 110, 118, 111, 107, 101, 0];
 
-    if cNames != 1 || lstrcmpW(*rgszNames, METHOD_NAME.as_ptr()) != 0 {
-        return DISP_E_UNKNOWNNAME;
+    // The "invokeAsync" string encoded in utf16.
+    const ASYNC_METHOD_NAME: [u16; 12] =
+        [105, 110, 118, 111, 107, 101, 65, 115, 121, 110, 99, 0];
+
+    if lstrcmpW(*rgszNames, METHOD_NAME.as_ptr()) == 0 {
+        *rgDispId = INVOKE_ID;
+        return S_OK;
     }
 
-    *rgDispId = 1;
-    S_OK
+    if lstrcmpW(*rgszNames, ASYNC_METHOD_NAME.as_ptr()) == 0 {
+        *rgDispId = INVOKE_ASYNC_ID;
+        return S_OK;
+    }
+
+    let client_site = ClientSite::from_dispatch(instance);
+
+    let handlers = &ClientSite::state(client_site).wide_handlers;
+
+    for (index, name) in handlers.iter().enumerate() {
+        if lstrcmpW(*rgszNames, name.as_ptr()) == 0 {
+            *rgDispId = INVOKE_ASYNC_ID + 1 + index as DISPID;
+            return S_OK;
+        }
+    }
+
+    // Scripts throw an exception when they call an unknown method.
+    DISP_E_UNKNOWNNAME
+}
+
+// The page assigns 'vst.invokeAsync' to 'external.invokeAsync' (see
+// 'deferred::SCRIPT'), the calls of the property are forwarded to it.
+unsafe fn invoke_async(
+    state: &State,
+    wFlags: WORD,
+    pDispParams: *mut DISPPARAMS,
+    pVarResult: *mut VARIANT,
+    pExcepInfo: *mut EXCEPINFO,
+    puArgErr: *mut UINT) -> HRESULT
+{
+    if wFlags & (DISPATCH_PROPERTYPUT | DISPATCH_PROPERTYPUTREF) != 0 {
+        if (*pDispParams).cArgs != 1 {
+            return DISP_E_BADPARAMCOUNT;
+        }
+
+        let argument = &*(*pDispParams).rgvarg;
+
+        if argument.n1.n2().vt as u32 != VT_DISPATCH {
+            return DISP_E_TYPEMISMATCH;
+        }
+
+        let function = *argument.n1.n2().n3.pdispVal();
+
+        if !function.is_null() {
+            (*function).AddRef();
+        }
+
+        *state.invoke_async.borrow_mut() = ComPointer::from_raw(function);
+        return S_OK;
+    }
+
+    // The function may reassign the property, so it's not borrowed during
+    // the call.
+    let function = state.invoke_async.borrow().as_ptr();
+
+    if function.is_null() {
+        return DISP_E_MEMBERNOTFOUND;
+    }
+
+    (*function).AddRef();
+    let function = ComPointer::from_raw(function);
+
+    if wFlags & DISPATCH_METHOD == 0 || (*pDispParams).cArgs == 0 {
+        if pVarResult != null_mut() {
+            VariantInit(pVarResult);
+            (*pVarResult).n1.n2_mut().vt = VT_DISPATCH as u16;
+            (*function.as_ptr()).AddRef();
+            *(*pVarResult).n1.n2_mut().n3.pdispVal_mut() = function.as_ptr();
+        }
+
+        return S_OK;
+    }
+
+    function.get().unwrap().Invoke(
+        DISPID_VALUE,
+        &IID_NULL,
+        LOCALE_USER_DEFAULT,
+        DISPATCH_METHOD,
+        pDispParams,
+        pVarResult,
+        pExcepInfo,
+        puArgErr)
 }
 
 unsafe extern "system" fn IDispatch_Invoke(
@@ -677,15 +826,32 @@ unsafe extern "system" fn IDispatch_Invoke(
     wFlags: WORD,
     pDispParams: *mut DISPPARAMS,
     pVarResult: *mut VARIANT,
-    _pExcepInfo: *mut EXCEPINFO,
+    pExcepInfo: *mut EXCEPINFO,
     puArgErr: *mut UINT) -> HRESULT
 {
     let client_site = ClientSite::from_dispatch(instance);
 
-    if dispIdMember != 1 {
-        return DISP_E_MEMBERNOTFOUND;
+    if dispIdMember == INVOKE_ASYNC_ID {
+        return invoke_async(
+            ClientSite::state(client_site),
+            wFlags,
+            pDispParams,
+            pVarResult,
+            pExcepInfo,
+            puArgErr);
     }
 
+    let handlers = &ClientSite::state(client_site).handlers;
+    let handler_count = handlers.len() as DISPID;
+
+    // 'None' stands for the 'invoke' method.
+    let handler = match dispIdMember - INVOKE_ASYNC_ID {
+        _ if dispIdMember == INVOKE_ID => None,
+        index if index > 0 && index <= handler_count =>
+            Some(&handlers[index as usize - 1]),
+        _ => return DISP_E_MEMBERNOTFOUND,
+    };
+
     if wFlags != DISPATCH_METHOD &&
         wFlags != DISPATCH_METHOD | DISPATCH_PROPERTYGET
     {
@@ -709,10 +875,6 @@ unsafe extern "system" fn IDispatch_Invoke(
     let argument = *(*(*pDispParams).rgvarg).n1.n2().n3.bstrVal();
     let argument_length = SysStringLen(argument);
 
-    use std::ffi::{OsStr, OsString};
-    use std::os::windows::ffi::{OsStrExt, OsStringExt};
-    use std::slice;
-
     let argument_utf8 =
         OsString::from_wide(
             slice::from_raw_parts(
@@ -724,7 +886,12 @@ unsafe extern "system" fn IDispatch_Invoke(
         return DISP_E_OVERFLOW;
     }
 
-    let result = ((*client_site).callback)(argument_utf8.unwrap());
+    let message = match handler {
+        Some(name) => handlers::message(name, &argument_utf8.unwrap()),
+        None => argument_utf8.unwrap(),
+    };
+
+    let result = ((*client_site).callback)(message);
 
     if pVarResult != null_mut() {
         VariantInit(pVarResult);
@@ -779,7 +946,7 @@ fn new_in_place_frame(window: HWND) -> ComPointer<IOleInPlaceFrame> {
                 lpVtbl: &OLE_IN_PLACE_FRAME_VTABLE
             },
             reference_counter: 1,
-            window: window,
+            window,
         });
 
     ComPointer::from_raw(
@@ -18,7 +18,7 @@ impl<T: Interface> ComPointer<T> {
 
     pub fn from_raw(pointer: *mut T) -> Self {
         ComPointer {
-            pointer: pointer,
+            pointer,
         }
     }
 
@@ -3,8 +3,10 @@
 use std::os::raw::*;
 
 // Non-asterisk imports are required to eliminate ambiguity
+use winapi::shared::basetsd::ULONG_PTR;
 use winapi::shared::guiddef::*;
 use winapi::shared::minwindef::*;
+use winapi::shared::minwindef::ULONG;
 use winapi::shared::windef::*;
 use winapi::shared::windef::SIZE;
 use winapi::shared::wtypes::*;
@@ -23,6 +25,14 @@ extern "system" {
         pUnknown: *mut IUnknown, fContained: BOOL) -> HRESULT;
 }
 
+#[link(name = "urlmon")]
+extern "system" {
+    pub fn CoInternetGetSession(
+        dwSessionMode: DWORD,
+        ppIInternetSession: *mut *mut IInternetSession,
+        dwReserved: DWORD) -> HRESULT;
+}
+
 #[link(name = "shlwapi")]
 extern "system" {
     pub fn SHCreateMemStream(pInit: *const BYTE, cbInit: UINT) -> *mut IStream;
@@ -35,9 +45,24 @@ pub type IOleCommandTarget = IUnknown;
 pub type IOleContainer = IUnknown;
 pub type IOleInPlaceActiveObject = IUnknown;
 pub type IHTMLWindow2 = IUnknown;
+pub type IInternetBindInfo = IUnknown;
+pub type PROTOCOLDATA = c_void;
+
+pub type OLECMDID = c_int;
+pub type READYSTATE = c_int;
+pub type OLECMDEXECOPT = c_int;
 
 pub const OLEIVERB_INPLACEACTIVATE: LONG = -5;
 
+pub const BINDSTATUS_MIMETYPEAVAILABLE: ULONG = 13;
+pub const BSCF_FIRSTDATANOTIFICATION: DWORD = 0x01;
+pub const BSCF_LASTDATANOTIFICATION: DWORD = 0x04;
+pub const BSCF_DATAFULLYAVAILABLE: DWORD = 0x08;
+pub const OLECMDID_OPTICAL_ZOOM: OLECMDID = 63;
+pub const OLECMDEXECOPT_DONTPROMPTUSER: OLECMDEXECOPT = 2;
+pub const READYSTATE_INTERACTIVE: READYSTATE = 3;
+pub const READYSTATE_COMPLETE: READYSTATE = 4;
+
 RIDL!{
     #[uuid(0x00000112, 0x0000, 0x0000, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46)]
     interface IOleObject(IOleObjectVtbl) : IUnknown(IUnknownVtbl) {
@@ -244,9 +269,16 @@ RIDL!{
     interface IWebBrowser2(IWebBrowser2Vtbl) : IWebBrowserApp(IWebBrowserAppVtbl) {
         fn Unused_Navigate2() -> HRESULT,
         fn Unused_QueryStatusWB() -> HRESULT,
-        fn Unused_ExecWB() -> HRESULT,
+        fn ExecWB(
+            cmdID: OLECMDID,
+            cmdexecopt: OLECMDEXECOPT,
+            pvaIn: *mut VARIANT,
+            pvaOut: *mut VARIANT,
+        ) -> HRESULT,
         fn Unused_ShowBrowserBar() -> HRESULT,
-        fn Unused_get_ReadyState() -> HRESULT,
+        fn get_ReadyState(
+            plReadyState: *mut READYSTATE,
+        ) -> HRESULT,
         fn Unused_get_Offline() -> HRESULT,
         fn Unused_put_Offline() -> HRESULT,
         fn Unused_get_Silent() -> HRESULT,
@@ -455,3 +487,74 @@ RIDL!{
         fn Unused_createStyleSheet() -> HRESULT,
     }
 }
+
+RIDL!{
+    #[uuid(0x79eac9e5, 0xbaf9, 0x11ce, 0x8c, 0x82, 0x00, 0xaa, 0x00, 0x4b, 0xa9, 0x0b)]
+    interface IInternetProtocolSink(IInternetProtocolSinkVtbl) : IUnknown(IUnknownVtbl) {
+        fn Switch(pProtocolData: *mut PROTOCOLDATA,) -> HRESULT,
+        fn ReportProgress(
+            ulStatusCode: ULONG,
+            szStatusText: LPCWSTR,) -> HRESULT,
+        fn ReportData(
+            grfBSCF: DWORD,
+            ulProgress: ULONG,
+            ulProgressMax: ULONG,) -> HRESULT,
+        fn ReportResult(
+            hrResult: HRESULT,
+            dwError: DWORD,
+            szResult: LPCWSTR,) -> HRESULT,
+    }
+}
+
+RIDL!{
+    #[uuid(0x79eac9e3, 0xbaf9, 0x11ce, 0x8c, 0x82, 0x00, 0xaa, 0x00, 0x4b, 0xa9, 0x0b)]
+    interface IInternetProtocolRoot(IInternetProtocolRootVtbl) : IUnknown(IUnknownVtbl) {
+        fn Start(
+            szUrl: LPCWSTR,
+            pOIProtSink: *mut IInternetProtocolSink,
+            pOIBindInfo: *mut IInternetBindInfo,
+            grfPI: DWORD,
+            dwReserved: ULONG_PTR,) -> HRESULT,
+        fn Continue(pProtocolData: *mut PROTOCOLDATA,) -> HRESULT,
+        fn Abort(hrReason: HRESULT, dwOptions: DWORD,) -> HRESULT,
+        fn Terminate(dwOptions: DWORD,) -> HRESULT,
+        fn Suspend() -> HRESULT,
+        fn Resume() -> HRESULT,
+    }
+}
+
+RIDL!{
+    #[uuid(0x79eac9e4, 0xbaf9, 0x11ce, 0x8c, 0x82, 0x00, 0xaa, 0x00, 0x4b, 0xa9, 0x0b)]
+    interface IInternetProtocol(IInternetProtocolVtbl) :
+        IInternetProtocolRoot(IInternetProtocolRootVtbl)
+    {
+        fn Read(pv: LPVOID, cb: ULONG, pcbRead: *mut ULONG,) -> HRESULT,
+        fn Seek(
+            dlibMove: LARGE_INTEGER,
+            dwOrigin: DWORD,
+            plibNewPosition: *mut ULARGE_INTEGER,) -> HRESULT,
+        fn LockRequest(dwOptions: DWORD,) -> HRESULT,
+        fn UnlockRequest() -> HRESULT,
+    }
+}
+
+RIDL!{
+    #[uuid(0x79eac9e7, 0xbaf9, 0x11ce, 0x8c, 0x82, 0x00, 0xaa, 0x00, 0x4b, 0xa9, 0x0b)]
+    interface IInternetSession(IInternetSessionVtbl) : IUnknown(IUnknownVtbl) {
+        fn RegisterNameSpace(
+            pCF: *mut IClassFactory,
+            rclsid: REFCLSID,
+            pwzProtocol: LPCWSTR,
+            cPatterns: ULONG,
+            ppwzPatterns: *const LPCWSTR,
+            dwReserved: DWORD,) -> HRESULT,
+        fn UnregisterNameSpace(
+            pCF: *mut IClassFactory,
+            pszProtocol: LPCWSTR,) -> HRESULT,
+        fn Unused_RegisterMimeFilter() -> HRESULT,
+        fn Unused_UnregisterMimeFilter() -> HRESULT,
+        fn Unused_CreateBinding() -> HRESULT,
+        fn Unused_SetSessionOption() -> HRESULT,
+        fn Unused_GetSessionOption() -> HRESULT,
+    }
+}
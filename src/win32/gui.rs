@@ -1,12 +1,14 @@
-use std::error::Error;
+use std::cell::RefCell;
 use std::ffi::OsStr;
-use std::mem::zeroed;
-use std::os::raw::c_void;
+use std::mem::{size_of, zeroed};
+use std::os::raw::{c_int, c_void};
 use std::os::windows::ffi::OsStrExt;
 use std::ptr::{null, null_mut};
 use std::rc::Rc;
+use std::slice;
 
 use winapi::Interface;
+use winapi::shared::basetsd::LONG_PTR;
 use winapi::shared::guiddef::*;
 use winapi::shared::minwindef::*;
 use winapi::shared::windef::*;
@@ -18,16 +20,40 @@ use winapi::um::oaidl::*;
 use winapi::um::oaidl::DISPID; // Required to eliminate ambiguity
 use winapi::um::objidlbase::*;
 use winapi::um::oleauto::*;
+use winapi::um::wingdi::{GetDeviceCaps, LOGPIXELSX};
 use winapi::um::winnt::*;
+use winapi::um::winnt::HRESULT; // Required to eliminate ambiguity
 use winapi::um::winuser::*;
 
-use lib::{JavascriptCallback, PluginGui};
+use error::{self, Error};
+use keyboard::Keyboard;
+use lib::{JavascriptCallback, Options, PluginGui};
+use script;
 use win32::client_site::*;
 use win32::com_pointer::*;
 use win32::ffi::*;
+use win32::keyboard;
+use win32::protocol::Registration;
+
+// Returns the code of a failed call.
+fn code(result: HRESULT) -> Option<i32> {
+    match result {
+        S_OK => None,
+        result => Some(result),
+    }
+}
+
+// Converts the string and frees it.
+unsafe fn take_bstr(value: BSTR) -> String {
+    if value.is_null() {
+        return String::new();
+    }
+
+    let result = String::from_utf16_lossy(
+        slice::from_raw_parts(value, SysStringLen(value) as usize));
 
-fn error(message: &str) -> Box<dyn Error> {
-    From::from(message)
+    SysFreeString(value);
+    result
 }
 
 struct Window {
@@ -40,7 +66,13 @@ impl Window {
         0x0070, 0x006c, 0x0075, 0x0067, 0x0069, 0x006e, 0x005f, 0x0077,
         0x0069, 0x006e, 0x0064, 0x006f, 0x0077, 0x0000];
 
-    pub fn new(parent: HWND, size: Option<(i32, i32)>) -> Window {
+    // The offset of the keyboard pointer in the window's extra memory.
+    const KEYBOARD_OFFSET: c_int = 0;
+
+    // The keyboard must outlive the window.
+    pub fn new(
+        parent: HWND, size: Option<(i32, i32)>, keyboard: &Keyboard) -> Window
+    {
         Window::register_window_class();
 
         let window_size = size.unwrap_or_else(|| Window::default_size());
@@ -63,8 +95,15 @@ impl Window {
                 null_mut())
         };
 
+        unsafe {
+            SetWindowLongPtrW(
+                handle,
+                Window::KEYBOARD_OFFSET,
+                keyboard as *const Keyboard as LONG_PTR);
+        }
+
         Window {
-            handle: handle,
+            handle,
         }
     }
 
@@ -82,6 +121,57 @@ impl Window {
         (width, height)
     }
 
+    // The browser is resized on 'WM_SIZE'.
+    fn resize(&self, size: (i32, i32)) {
+        unsafe {
+            SetWindowPos(
+                self.handle,
+                null_mut(), /*insert_after*/
+                0, /*x*/
+                0, /*y*/
+                size.0,
+                size.1,
+                SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE);
+        }
+    }
+
+    // Displays the text in a read-only edit control so it can be selected
+    // and copied.
+    fn add_error_panel(&self, text: &str) {
+        let class_name: Vec<u16> = OsStr::new("EDIT")
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+        let text: Vec<u16> = OsStr::new(&text.replace('\n', "\r\n"))
+            .encode_wide()
+            .chain(Some(0))
+            .collect();
+
+        let mut rectangle =
+            RECT {left: 0, top: 0, right: 0, bottom: 0};
+
+        unsafe {
+            const STYLE: DWORD = WS_CHILD | WS_VISIBLE | WS_VSCROLL |
+                ES_MULTILINE | ES_READONLY | ES_AUTOVSCROLL;
+
+            GetClientRect(self.handle, &mut rectangle);
+
+            CreateWindowExW(
+                0, /*extended_style*/
+                class_name.as_ptr(),
+                text.as_ptr(),
+                STYLE,
+                0, /*x*/
+                0, /*y*/
+                rectangle.right - rectangle.left,
+                rectangle.bottom - rectangle.top,
+                self.handle,
+                null_mut(), /*menu*/
+                GetModuleHandleW(null()),
+                null_mut());
+        }
+    }
+
     fn default_size() -> (i32, i32) {
         unsafe {
             let width = GetSystemMetrics(SM_CXSCREEN) / 2;
@@ -96,7 +186,7 @@ impl Window {
             style: CS_DBLCLKS,
             lpfnWndProc: Some(Window::window_procedure),
             cbClsExtra: 0,
-            cbWndExtra: 0,
+            cbWndExtra: size_of::<LONG_PTR>() as c_int,
             hInstance: unsafe { GetModuleHandleW(null()) },
             hIcon: null_mut(),
             hCursor: unsafe { LoadCursorW(null_mut(), IDC_ARROW) },
@@ -114,8 +204,30 @@ impl Window {
         handle: HWND, message: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT
     {
         match message {
-            WM_GETDLGCODE => {
-                return DLGC_WANTALLKEYS;
+            WM_GETDLGCODE => unsafe {
+                // The keyboard is stored by 'Window::new'.
+                let keyboard = GetWindowLongPtrW(
+                    handle, Window::KEYBOARD_OFFSET) as *const Keyboard;
+                let key_message = lparam as *const MSG;
+
+                // Without a message the dialog manager asks about all keys.
+                if keyboard.is_null() || key_message.is_null() ||
+                    keyboard::consumes(&*keyboard, &*key_message)
+                {
+                    return DLGC_WANTALLKEYS;
+                }
+
+                return 0;
+            },
+            WM_SIZE => unsafe {
+                // The browser is stored by 'WebBrowser::new'.
+                let browser = GetWindowLongPtrW(handle, GWLP_USERDATA)
+                    as *mut IWebBrowser2;
+
+                if !browser.is_null() {
+                    (*browser).put_Width(LOWORD(lparam as DWORD) as LONG);
+                    (*browser).put_Height(HIWORD(lparam as DWORD) as LONG);
+                }
             },
             _ => {}
         }
@@ -127,34 +239,74 @@ impl Window {
 
 struct WebBrowser {
     browser: ComPointer<IWebBrowser2>,
+    window_handle: HWND,
+    registration: Option<Registration>,
+    // Runs on the pages opened with 'Options::url'. The last document the
+    // script ran on is kept alive so its address can't be reused.
+    startup_script: String,
+    started_document: RefCell<ComPointer<IDispatch>>,
+    // Replaces the page of 'Options::url' (see 'replace_page').
+    pending_document: RefCell<Option<String>>,
 }
 
 impl WebBrowser {
     fn new(
         window_handle: HWND,
         html_document: String,
-        js_callback: Rc<JavascriptCallback>) ->
-            Result<WebBrowser, Box<dyn Error>>
+        js_callback: Rc<JavascriptCallback>,
+        options: &Options) -> Result<WebBrowser, Error>
     {
         unsafe {
             OleInitialize(null_mut());
         }
 
+        let registration = match options.assets.clone() {
+            Some(assets) => Some(
+                Registration::new(assets)
+                    .ok_or(
+                        error::bridge(
+                            "Couldn't register the 'plugin' protocol \
+                            handler"))?),
+            None => None,
+        };
+
         let browser = WebBrowser {
-            browser: WebBrowser::new_browser_com_object()?
+            browser: WebBrowser::new_browser_com_object()?,
+            window_handle,
+            registration,
+            startup_script: options.startup_script.clone(),
+            started_document: RefCell::new(ComPointer::new()),
+            pending_document: RefCell::new(None),
         };
 
-        browser.embed(window_handle, js_callback)?;
+        browser.embed(window_handle, js_callback, options)?;
+
+        // Allows the window to resize the browser. The window outlives the
+        // browser so the pointer is reset when the browser is dropped.
+        unsafe {
+            SetWindowLongPtrW(
+                window_handle,
+                GWLP_USERDATA,
+                browser.browser.as_ptr() as LONG_PTR);
+        }
 
         // https://msdn.microsoft.com/library/aa752047
-        browser.open_blank_page()?;
-        browser.load_html_document(html_document)?;
+        match options.url {
+            // The page loads asynchronously, the startup script runs on idle
+            // once the document is interactive.
+            Some(ref url) => browser.navigate(url)?,
+            None => {
+                browser.open_blank_page()?;
+                browser.load_html_document(html_document)?;
+            },
+        }
+        browser.set_zoom(options.zoom.unwrap_or(1.0));
 
         Ok(browser)
     }
 
     fn new_browser_com_object() ->
-        Result<ComPointer<IWebBrowser2>, Box<dyn Error>>
+        Result<ComPointer<IWebBrowser2>, Error>
     {
         let mut web_browser = ComPointer::<IWebBrowser2>::new();
 
@@ -172,7 +324,11 @@ impl WebBrowser {
         if result == S_OK && web_browser.get().is_some() {
             Ok(web_browser)
         } else {
-            Err(error("Couldn't get an instance of the 'IWebBrowser2' class"))
+            Err(Error::BackendInitialization {
+                message: String::from(
+                    "Couldn't get an instance of the 'IWebBrowser2' class"),
+                code: code(result),
+            })
         }
     }
 
@@ -182,33 +338,94 @@ impl WebBrowser {
             .unwrap()
     }
 
-    fn open_blank_page(&self) -> Result<(), Box<dyn Error>> {
-        let url_buffer: Vec<u16> =
-            OsStr::new("about:blank").encode_wide().collect();
+    fn navigate(&self, url: &str) -> Result<(), Error> {
+        let url_buffer: Vec<u16> = OsStr::new(url).encode_wide().collect();
 
-        unsafe {
+        let result = unsafe {
             let url = SysAllocStringLen(
                 url_buffer.as_ptr(),
                 url_buffer.len() as u32);
 
-            if self.browser().Navigate(
+            let result = self.browser().Navigate(
                 url,
                 null_mut(),
                 null_mut(),
                 null_mut(),
-                null_mut()) != S_OK
-            {
-                return Err(error("Couldn't open a blank page"))
-            }
+                null_mut());
 
             SysFreeString(url);
+            result
+        };
+
+        match result {
+            S_OK => Ok(()),
+            result => Err(Error::DocumentLoading {
+                message: format!("Couldn't open '{}'", url),
+                code: Some(result),
+            }),
         }
+    }
 
+    fn open_blank_page(&self) -> Result<(), Error> {
+        self.navigate("about:blank")
+    }
+
+    // Pages of other URLs are replaced by the blank page asynchronously, so
+    // the document is loaded on idle once the blank page is complete.
+    fn replace_page(&self, html_document: String) -> Result<(), Error> {
+        self.open_blank_page()?;
+        *self.pending_document.borrow_mut() = Some(html_document);
         Ok(())
     }
 
+    fn load_pending_document(&self) {
+        if self.pending_document.borrow().is_none() {
+            return;
+        }
+
+        let mut state: READYSTATE = 0;
+
+        let is_complete = unsafe {
+            self.browser().get_ReadyState(&mut state) == S_OK &&
+                state == READYSTATE_COMPLETE
+        };
+
+        if is_complete {
+            let html_document = self.pending_document.borrow_mut().take();
+            let _ = self.load_html_document(html_document.unwrap());
+        }
+    }
+
+    // Internet Explorer has no user scripts, so the script is executed once
+    // per document as soon as the document becomes interactive.
+    fn run_startup_script(&self) {
+        if self.startup_script.is_empty() {
+            return;
+        }
+
+        let document = match self.document_dispatch() {
+            Ok(document) => document,
+            Err(_) => return,
+        };
+
+        if document.as_ptr() == self.started_document.borrow().as_ptr() {
+            return;
+        }
+
+        let mut state: READYSTATE = 0;
+
+        let is_interactive = unsafe {
+            self.browser().get_ReadyState(&mut state) == S_OK &&
+                state >= READYSTATE_INTERACTIVE
+        };
+
+        if is_interactive && self.execute(&self.startup_script).is_ok() {
+            *self.started_document.borrow_mut() = document;
+        }
+    }
+
     fn document_dispatch(&self) ->
-        Result<ComPointer<IDispatch>, Box<dyn Error>>
+        Result<ComPointer<IDispatch>, Error>
     {
         let mut result = ComPointer::<IDispatch>::new();
 
@@ -220,13 +437,13 @@ impl WebBrowser {
         match success {
             true => Ok(result),
             false => Err(
-                error(
+                error::loading(
                     "The 'IWebBrowser2::get_Document' method returned an \
                     error")),
         }
     }
 
-    fn window_dispatch(&self) -> Result<ComPointer<IDispatch>, Box<dyn Error>> {
+    fn window_dispatch(&self) -> Result<ComPointer<IDispatch>, Error> {
         let document_dispatch = self.document_dispatch()?;
 
         let window_dispatch = document_dispatch
@@ -252,18 +469,28 @@ impl WebBrowser {
             Ok(window_dispatch)
         } else {
             Err(
-                error(
+                error::loading(
                     "Couldn't get an instance of the 'IDispatch' class \
                     for the document window"))
         }
     }
 
     fn load_html_document(
-        &self, html_document: String) -> Result<(), Box<dyn Error>>
+        &self, html_document: String) -> Result<(), Error>
     {
-        // TODO: do not assume the document is ready
+        // The initial blank page is ready right after 'open_blank_page'.
+        // Scripts are queued by 'PluginGui' until the loaded document reports
+        // that it's ready.
         let document_dispatch = self.document_dispatch()?;
 
+        // Documents are loaded into 'about:blank' pages, so relative URLs
+        // refer to the assets only with the base URL set.
+        let html_document = match self.registration {
+            Some(ref registration) => script::set_base_url(
+                &html_document, &registration.base_url()),
+            None => html_document,
+        };
+
         let stream = ComPointer::<IStream>::from_raw(
             unsafe {
                 SHCreateMemStream(
@@ -273,39 +500,50 @@ impl WebBrowser {
 
         stream
             .get()
-            .ok_or(error("Couldn't get an instance of the 'IStream' class"))?;
+            .ok_or(
+                error::loading(
+                    "Couldn't get an instance of the 'IStream' class"))?;
+
+        let persist_stream =
+            document_dispatch.query_interface::<IPersistStreamInit>();
 
-        let success = document_dispatch
-            .query_interface::<IPersistStreamInit>()
+        let result = persist_stream
             .get()
             .map(|persist_stream| {
                 unsafe {
-                    persist_stream.InitNew() == S_OK &&
-                    persist_stream.Load(stream.as_ptr()) == S_OK
+                    match persist_stream.InitNew() {
+                        S_OK => persist_stream.Load(stream.as_ptr()),
+                        result => result,
+                    }
                 }
             })
             .ok_or(
-                error(
+                error::loading(
                     "Couldn't get an instance of the 'IPersistStreamInit' \
                     class"))?;
 
-        match success {
-            true => Ok(()),
-            false => Err(error("Couldn't load an HTML document")),
+        match result {
+            S_OK => Ok(()),
+            result => Err(Error::DocumentLoading {
+                message: String::from("Couldn't load an HTML document"),
+                code: Some(result),
+            }),
         }
     }
 
     fn embed(
         &self,
         window_handle: HWND,
-        js_callback: Rc<JavascriptCallback>) -> Result<(), Box<dyn Error>>
+        js_callback: Rc<JavascriptCallback>,
+        options: &Options) -> Result<(), Error>
     {
         let ole_object = self.browser.query_interface::<IOleObject>();
 
         ole_object
             .get()
             .ok_or(
-                error("Couldn't get an instance of the 'IOleObject' class"))?;
+                error::initialization(
+                    "Couldn't get an instance of the 'IOleObject' class"))?;
 
         let ole_in_place_object =
             ole_object.query_interface::<IOleInPlaceObject>();
@@ -313,12 +551,16 @@ impl WebBrowser {
         ole_in_place_object
             .get()
             .ok_or(
-                error(
+                error::initialization(
                     "Couldn't get an instance of the 'IOleInPlaceObject' \
                     class"))?;
 
         let client_site = new_client_site(
-            window_handle, ole_in_place_object, js_callback);
+            window_handle,
+            ole_in_place_object,
+            js_callback,
+            options.handlers.clone(),
+            options.keyboard.clone());
 
         let success = {
             let mut rectangle = RECT {left: 0, top: 0, right: 0, bottom: 0};
@@ -343,12 +585,59 @@ impl WebBrowser {
 
         match success {
             true => Ok(()),
-            false => Err(error("Couldn't reveal an HTML browser")),
+            false => Err(
+                error::initialization("Couldn't reveal an HTML browser")),
         }
     }
 
-    fn execute(&self, javascript_code: &str) -> Result<(), Box<dyn Error>> {
+    // The optical zoom scales the page like the browser's zoom control does.
+    fn set_zoom(&self, zoom: f64) {
+        unsafe {
+            let mut value: VARIANT = zeroed();
+
+            VariantInit(&mut value);
+
+            value.n1.n2_mut().vt = VT_I4 as u16;
+            *value.n1.n2_mut().n3.lVal_mut() = (zoom * 100.0).round() as i32;
+
+            self.browser().ExecWB(
+                OLECMDID_OPTICAL_ZOOM,
+                OLECMDEXECOPT_DONTPROMPTUSER,
+                &mut value,
+                null_mut());
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
+        self.eval(javascript_code, null_mut())
+    }
+
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        unsafe {
+            let mut result: VARIANT = zeroed();
+
+            VariantInit(&mut result);
+
+            self.eval(javascript_code, &mut result)?;
+
+            if result.n1.n2().vt == VT_BSTR as u16 {
+                // The string is freed here.
+                Ok(take_bstr(*result.n1.n2().n3.bstrVal()))
+            } else {
+                VariantClear(&mut result);
+                Err(error::evaluation("The result is not a string"))
+            }
+        }
+    }
+
+    // Calls the 'window.eval' function. The returned value is stored to
+    // 'value' if it's not null.
+    fn eval(&self, javascript_code: &str, value: *mut VARIANT) ->
+        Result<(), Error>
+    {
         let window_dispatch = self.window_dispatch()?;
+        // TODO: cache the 'window_dispatch' object and the method id
+        let method_id = WebBrowser::window_eval_method_id(&window_dispatch)?;
 
         let argument_value: Vec<u16> = OsStr::new(javascript_code)
             .encode_wide()
@@ -356,6 +645,7 @@ impl WebBrowser {
 
         unsafe {
             let mut argument: VARIANT = zeroed();
+            let mut exception: EXCEPINFO = zeroed();
 
             VariantInit(&mut argument);
 
@@ -371,33 +661,51 @@ impl WebBrowser {
                 cNamedArgs: 0,
             };
 
-            // TODO: cache the 'window_dispatch' object and the method id
-
-            let result = if window_dispatch
+            let result = window_dispatch
                 .get()
                 .unwrap()
                 .Invoke(
-                    WebBrowser::window_eval_method_id(&window_dispatch)?,
+                    method_id,
                     &IID_NULL,
                     LOCALE_SYSTEM_DEFAULT,
                     DISPATCH_METHOD,
                     &mut parameters,
-                    null_mut(),
-                    null_mut(),
-                    null_mut()) == S_OK
-            {
-                Ok(())
-            } else {
-                Err(error("Execution of the Javascript code failed"))
-            };
+                    value,
+                    &mut exception,
+                    null_mut());
 
             VariantClear(&mut argument);
-            result
+
+            if result == DISP_E_EXCEPTION {
+                if let Some(fill_exception) = exception.pfnDeferredFillIn {
+                    fill_exception(&mut exception);
+                }
+            }
+
+            // The strings must be freed in any case.
+            let description = take_bstr(exception.bstrDescription);
+            take_bstr(exception.bstrSource);
+            take_bstr(exception.bstrHelpFile);
+
+            match result {
+                S_OK => Ok(()),
+                result => Err(Error::ScriptEvaluation {
+                    message: if description.is_empty() {
+                        String::from("Execution of the Javascript code failed")
+                    } else {
+                        description
+                    },
+                    code: Some(match exception.scode {
+                        0 => result,
+                        scode => scode,
+                    }),
+                }),
+            }
         }
     }
 
     fn window_eval_method_id(window_dispatch: &ComPointer<IDispatch>) ->
-        Result<DISPID, Box<dyn Error>>
+        Result<DISPID, Error>
     {
         assert!(window_dispatch.get().is_some());
 
@@ -419,7 +727,18 @@ impl WebBrowser {
         if result == S_OK {
             Ok(id)
         } else {
-            Err(error("Couldn't get an ID for the 'eval' method"))
+            Err(Error::ScriptEvaluation {
+                message: String::from("Couldn't get an ID for the 'eval' method"),
+                code: Some(result),
+            })
+        }
+    }
+}
+
+impl Drop for WebBrowser {
+    fn drop(&mut self) {
+        unsafe {
+            SetWindowLongPtrW(self.window_handle, GWLP_USERDATA, 0);
         }
     }
 }
@@ -429,7 +748,7 @@ struct Gui {
     js_callback: Rc<JavascriptCallback>,
     web_browser: Option<WebBrowser>,
     window: Option<Window>,
-    window_size: Option<(i32, i32)>,
+    options: Options,
 }
 
 impl PluginGui for Gui {
@@ -449,31 +768,117 @@ impl PluginGui for Gui {
         self.window = None;
     }
 
-    fn open(&mut self, parent_handle: *mut c_void) -> bool {
-        let window = Window::new(parent_handle as HWND, self.window_size);
+    fn open(&mut self, parent_handle: *mut c_void) -> Result<(), Error> {
+        let window = Window::new(
+            parent_handle as HWND,
+            self.options.window_size,
+            &self.options.keyboard);
+
+        if window.handle.is_null() {
+            return Err(error::initialization("Couldn't create a window"));
+        }
 
-        match WebBrowser::new(
+        let browser = WebBrowser::new(
             window.handle,
             self.html_document.clone(),
-            self.js_callback.clone()) {
+            self.js_callback.clone(),
+            &self.options);
+
+        let result = match browser {
             Ok(browser) => {
-                self.window = Some(window);
                 self.web_browser = Some(browser);
-                true
+                Ok(())
             },
-            Err(_) => false // TODO: Display errors
-        }
+            Err(error) => {
+                if !self.options.error_page {
+                    return Err(error);
+                }
+
+                window.add_error_panel(&error::report(&error));
+                Err(error)
+            }
+        };
+
+        self.window = Some(window);
+        result
     }
 
     fn is_open(&mut self) -> bool {
         self.window.is_some()
     }
 
-    fn execute(&self, javascript_code: &str) -> Result<(), Box<dyn Error>> {
+    fn load(&mut self, html_document: String) -> Result<(), Error> {
+        self.html_document = html_document;
+
+        let url = self.options.url.take();
+
+        match self.web_browser {
+            Some(ref web_browser) => {
+                // The document of an arbitrary URL can't be rewritten.
+                if url.is_some() ||
+                    web_browser.pending_document.borrow().is_some()
+                {
+                    return web_browser.replace_page(
+                        self.html_document.clone());
+                }
+
+                web_browser.load_html_document(self.html_document.clone())
+            },
+            None => Ok(()),
+        }
+    }
+
+    fn resize(&mut self, width: i32, height: i32) {
+        self.options.window_size = Some((width, height));
+
+        if let Some(ref window) = self.window {
+            window.resize((width, height));
+        }
+    }
+
+    fn set_zoom(&mut self, zoom: f64) {
+        self.options.zoom = Some(zoom);
+
+        if let Some(ref web_browser) = self.web_browser {
+            web_browser.set_zoom(zoom);
+        }
+    }
+
+    // Internet Explorer scales pages to the system DPI by itself.
+    fn system_scale_factor(&self) -> f64 {
+        unsafe {
+            let context = GetDC(null_mut());
+            let dpi = GetDeviceCaps(context, LOGPIXELSX);
+
+            ReleaseDC(null_mut(), context);
+
+            match dpi {
+                0 => 1.0,
+                dpi => dpi as f64 / 96.0,
+            }
+        }
+    }
+
+    fn idle(&mut self) {
+        if let Some(ref web_browser) = self.web_browser {
+            web_browser.load_pending_document();
+            web_browser.run_startup_script();
+        }
+    }
+
+    fn execute(&self, javascript_code: &str) -> Result<(), Error> {
         if let Some(ref web_browser) = self.web_browser {
             web_browser.execute(javascript_code)
         } else {
-            Err(error("The plugin window is closed"))
+            Err(Error::WindowClosed)
+        }
+    }
+
+    fn evaluate(&self, javascript_code: &str) -> Result<String, Error> {
+        if let Some(ref web_browser) = self.web_browser {
+            web_browser.evaluate(javascript_code)
+        } else {
+            Err(Error::WindowClosed)
         }
     }
 }
@@ -481,14 +886,14 @@ impl PluginGui for Gui {
 pub fn new_plugin_gui(
     html_document: String,
     js_callback: JavascriptCallback,
-    window_size: Option<(i32, i32)>) -> Box<dyn PluginGui>
+    options: Options) -> Box<dyn PluginGui>
 {
     Box::new(
         Gui {
-            html_document: html_document,
+            html_document,
             js_callback: Rc::new(js_callback),
             web_browser: None,
             window: None,
-            window_size: window_size,
+            options,
         })
 }
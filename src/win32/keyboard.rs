@@ -0,0 +1,151 @@
+use std::char;
+use std::os::raw::c_int;
+
+use winapi::shared::minwindef::*;
+use winapi::shared::windef::HWND;
+use winapi::um::winuser::*;
+
+use vst::editor::Key;
+
+use keyboard::{KeyPress, Keyboard};
+
+fn key(virtual_key: c_int) -> Option<Key> {
+    let key = match virtual_key {
+        VK_BACK => Key::Back,
+        VK_TAB => Key::Tab,
+        VK_CLEAR => Key::Clear,
+        VK_RETURN => Key::Return,
+        VK_PAUSE => Key::Pause,
+        VK_ESCAPE => Key::Escape,
+        VK_END => Key::End,
+        VK_HOME => Key::Home,
+        VK_LEFT => Key::Left,
+        VK_UP => Key::Up,
+        VK_RIGHT => Key::Right,
+        VK_DOWN => Key::Down,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_SELECT => Key::Select,
+        VK_PRINT => Key::Print,
+        VK_SNAPSHOT => Key::Snapshot,
+        VK_INSERT => Key::Insert,
+        VK_DELETE => Key::Delete,
+        VK_HELP => Key::Help,
+        VK_NUMPAD0 => Key::Numpad0,
+        VK_NUMPAD1 => Key::Numpad1,
+        VK_NUMPAD2 => Key::Numpad2,
+        VK_NUMPAD3 => Key::Numpad3,
+        VK_NUMPAD4 => Key::Numpad4,
+        VK_NUMPAD5 => Key::Numpad5,
+        VK_NUMPAD6 => Key::Numpad6,
+        VK_NUMPAD7 => Key::Numpad7,
+        VK_NUMPAD8 => Key::Numpad8,
+        VK_NUMPAD9 => Key::Numpad9,
+        VK_MULTIPLY => Key::Multiply,
+        VK_ADD => Key::Add,
+        VK_SEPARATOR => Key::Separator,
+        VK_SUBTRACT => Key::Subtract,
+        VK_DECIMAL => Key::Decimal,
+        VK_DIVIDE => Key::Divide,
+        VK_F1 => Key::F1,
+        VK_F2 => Key::F2,
+        VK_F3 => Key::F3,
+        VK_F4 => Key::F4,
+        VK_F5 => Key::F5,
+        VK_F6 => Key::F6,
+        VK_F7 => Key::F7,
+        VK_F8 => Key::F8,
+        VK_F9 => Key::F9,
+        VK_F10 => Key::F10,
+        VK_F11 => Key::F11,
+        VK_F12 => Key::F12,
+        VK_NUMLOCK => Key::Numlock,
+        VK_SCROLL => Key::Scroll,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Control,
+        VK_MENU => Key::Alt,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn modifiers() -> u8 {
+    let is_pressed = |virtual_key| unsafe {
+        GetKeyState(virtual_key) as u16 & 0x8000 != 0
+    };
+
+    let mut modifiers = 0;
+
+    if is_pressed(VK_SHIFT) {
+        modifiers |= KeyPress::SHIFT;
+    }
+
+    if is_pressed(VK_MENU) {
+        modifiers |= KeyPress::ALT;
+    }
+
+    if is_pressed(VK_CONTROL) {
+        modifiers |= KeyPress::CONTROL;
+    }
+
+    modifiers
+}
+
+// Returns 'None' for messages other than key presses and releases.
+fn key_press(message: &MSG) -> Option<KeyPress> {
+    match message.message {
+        WM_KEYDOWN | WM_KEYUP | WM_SYSKEYDOWN | WM_SYSKEYUP => {},
+        _ => return None,
+    }
+
+    let virtual_key = message.wParam as c_int;
+
+    if let Some(key) = key(virtual_key) {
+        return Some(KeyPress {
+            character: None,
+            key: Some(key),
+            modifiers: modifiers(),
+        });
+    }
+
+    // Letters are mapped to upper case characters.
+    let character = unsafe {
+        MapVirtualKeyW(virtual_key as UINT, MAPVK_VK_TO_CHAR) & 0x7fff_ffff
+    };
+
+    Some(KeyPress {
+        character: char::from_u32(character)
+            .filter(|&character| character != '\0')
+            .map(|character| character.to_ascii_lowercase()),
+        key: None,
+        modifiers: modifiers(),
+    })
+}
+
+// Returns 'true' if the message is a key which goes to the page.
+pub fn consumes(keyboard: &Keyboard, message: &MSG) -> bool {
+    key_press(message)
+        .map(|key_press| keyboard.consumes(&key_press))
+        .unwrap_or(true)
+}
+
+// Re-posts the key to the top-level window where hosts handle their
+// shortcuts. Returns 'false' if the key goes to the page.
+pub fn forward(keyboard: &Keyboard, window: HWND, message: &MSG) -> bool {
+    if consumes(keyboard, message) {
+        return false;
+    }
+
+    unsafe {
+        let root = GetAncestor(window, GA_ROOT);
+
+        if root.is_null() || root == window {
+            return false;
+        }
+
+        PostMessageW(root, message.message, message.wParam, message.lParam);
+    }
+
+    true
+}
@@ -2,6 +2,9 @@
 mod client_site;
 mod com_pointer;
 mod gui;
+mod keyboard;
+#[allow(non_snake_case)]
+mod protocol;
 #[allow(dead_code, non_snake_case, non_upper_case_globals)]
 mod ffi;
 
@@ -0,0 +1,495 @@
+// A temporary pluggable protocol handler which serves assets from the
+// 'plugin://' URLs.
+//
+// The handler is registered for the current process and shared by all plugin
+// windows. Each window serves its assets from its own host (e.g.
+// 'plugin://app-1/') so the documents of different windows don't get each
+// other's assets. The handler is unregistered along with the last window.
+
+use std::borrow::Cow;
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::iter::once;
+use std::os::windows::ffi::OsStrExt;
+use std::ptr::null_mut;
+use std::rc::{Rc, Weak};
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Non-asterisk imports are required to eliminate ambiguity
+use winapi::Interface;
+use winapi::ctypes::*;
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::guiddef::*;
+use winapi::shared::minwindef::*;
+use winapi::shared::minwindef::ULONG;
+use winapi::shared::winerror::*;
+use winapi::um::unknwnbase::*;
+use winapi::um::winnt::{LARGE_INTEGER, LPCWSTR, ULARGE_INTEGER};
+
+use assets::{self, AssetProvider};
+use win32::com_pointer::ComPointer;
+use win32::ffi::*;
+
+// The class ID doesn't matter since the class is never registered in the
+// system registry.
+const CLSID_PLUGIN_PROTOCOL: GUID = GUID {
+    Data1: 0x4f6a_51c2,
+    Data2: 0x7b0e,
+    Data3: 0x4d7d,
+    Data4: [0x9a, 0x3c, 0x1e, 0x52, 0x8b, 0x60, 0xd4, 0x17],
+};
+
+fn wide_string(value: &str) -> Vec<u16> {
+    OsStr::new(value).encode_wide().chain(once(0)).collect()
+}
+
+const SCHEME: &str = "plugin";
+
+// Asset providers of the open windows by their hosts.
+type Hosts = RefCell<HashMap<String, Rc<dyn AssetProvider>>>;
+
+// Returns the asset provider of the window the URL belongs to and the asset
+// path.
+fn find_asset(
+    hosts: &Hosts, url: &str) -> Option<(Rc<dyn AssetProvider>, String)>
+{
+    let url = url.strip_prefix(SCHEME)?.strip_prefix("://")?;
+    let host_end = url.find('/').unwrap_or(url.len());
+
+    let assets = hosts.borrow().get(&url[..host_end])?.clone();
+
+    // 'resource_path' expects URLs of the 'plugin://app/' form.
+    let path = url[host_end..].trim_start_matches('/');
+    let path = assets::resource_path(
+        &format!("{}{}", assets::BASE_URL, path))?;
+
+    Some((assets, path))
+}
+
+unsafe fn from_wide_string(value: LPCWSTR) -> String {
+    let mut length = 0;
+
+    while *value.offset(length) != 0 {
+        length += 1;
+    }
+
+    String::from_utf16_lossy(slice::from_raw_parts(value, length as usize))
+}
+
+#[repr(C)]
+struct Protocol {
+    internet_protocol: IInternetProtocol,
+    reference_counter: ULONG,
+    hosts: Rc<Hosts>,
+    content: Cow<'static, [u8]>,
+    position: usize,
+}
+
+#[repr(C)]
+struct ClassFactory {
+    class_factory: IClassFactory,
+    reference_counter: ULONG,
+    hosts: Rc<Hosts>,
+}
+
+const INTERNET_PROTOCOL_VTABLE: IInternetProtocolVtbl = IInternetProtocolVtbl {
+    parent: IInternetProtocolRootVtbl {
+        parent: IUnknownVtbl {
+            AddRef:         IInternetProtocol_AddRef,
+            Release:        IInternetProtocol_Release,
+            QueryInterface: IInternetProtocol_QueryInterface,
+        },
+        Start:     IInternetProtocol_Start,
+        Continue:  IInternetProtocol_Continue,
+        Abort:     IInternetProtocol_Abort,
+        Terminate: IInternetProtocol_Terminate,
+        Suspend:   IInternetProtocol_Suspend,
+        Resume:    IInternetProtocol_Resume,
+    },
+    Read:          IInternetProtocol_Read,
+    Seek:          IInternetProtocol_Seek,
+    LockRequest:   IInternetProtocol_LockRequest,
+    UnlockRequest: IInternetProtocol_UnlockRequest,
+};
+
+const CLASS_FACTORY_VTABLE: IClassFactoryVtbl = IClassFactoryVtbl {
+    parent: IUnknownVtbl {
+        AddRef:         IClassFactory_AddRef,
+        Release:        IClassFactory_Release,
+        QueryInterface: IClassFactory_QueryInterface,
+    },
+    CreateInstance: IClassFactory_CreateInstance,
+    LockServer:     IClassFactory_LockServer,
+};
+
+unsafe extern "system" fn IInternetProtocol_AddRef(
+    instance: *mut IUnknown) -> ULONG
+{
+    let protocol = instance as *mut Protocol;
+
+    (*protocol).reference_counter += 1;
+    (*protocol).reference_counter
+}
+
+unsafe extern "system" fn IInternetProtocol_Release(
+    instance: *mut IUnknown) -> ULONG
+{
+    let protocol = instance as *mut Protocol;
+
+    let result = {
+        (*protocol).reference_counter -= 1;
+        (*protocol).reference_counter
+    };
+
+    assert!(result != ULONG::max_value());
+
+    if result == 0 {
+        drop(Box::from_raw(protocol));
+    }
+
+    result
+}
+
+unsafe extern "system" fn IInternetProtocol_QueryInterface(
+    instance: *mut IUnknown,
+    riid: REFIID,
+    ppvObject: *mut *mut c_void) -> HRESULT
+{
+    *ppvObject = if IsEqualGUID(&*riid, &IUnknown::uuidof()) ||
+        IsEqualGUID(&*riid, &IInternetProtocolRoot::uuidof()) ||
+        IsEqualGUID(&*riid, &IInternetProtocol::uuidof())
+    {
+        instance as *mut c_void
+    } else {
+        null_mut()
+    };
+
+    if !(*ppvObject).is_null() {
+        (*instance).AddRef();
+        S_OK
+    } else {
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn IInternetProtocol_Start(
+    instance: *mut IInternetProtocolRoot,
+    szUrl: LPCWSTR,
+    pOIProtSink: *mut IInternetProtocolSink,
+    _pOIBindInfo: *mut IInternetBindInfo,
+    _grfPI: DWORD,
+    _dwReserved: ULONG_PTR) -> HRESULT
+{
+    let protocol = instance as *mut Protocol;
+
+    let asset = find_asset(&(*protocol).hosts, &from_wide_string(szUrl))
+        .and_then(|(assets, path)| assets.get(&path));
+
+    let asset = match asset {
+        Some(asset) => asset,
+        None => return INET_E_RESOURCE_NOT_FOUND,
+    };
+
+    let size = asset.content.len() as ULONG;
+    let mime_type = wide_string(&asset.mime_type);
+
+    (*protocol).content = asset.content;
+    (*protocol).position = 0;
+
+    // The whole content is available right away.
+    (*pOIProtSink).ReportProgress(
+        BINDSTATUS_MIMETYPEAVAILABLE, mime_type.as_ptr());
+    (*pOIProtSink).ReportData(
+        BSCF_FIRSTDATANOTIFICATION |
+            BSCF_LASTDATANOTIFICATION |
+            BSCF_DATAFULLYAVAILABLE,
+        size,
+        size);
+    (*pOIProtSink).ReportResult(S_OK, 0, null_mut());
+
+    S_OK
+}
+
+unsafe extern "system" fn IInternetProtocol_Continue(
+    _instance: *mut IInternetProtocolRoot,
+    _pProtocolData: *mut PROTOCOLDATA) -> HRESULT
+{
+    S_OK
+}
+
+unsafe extern "system" fn IInternetProtocol_Abort(
+    _instance: *mut IInternetProtocolRoot,
+    _hrReason: HRESULT,
+    _dwOptions: DWORD) -> HRESULT
+{
+    S_OK
+}
+
+unsafe extern "system" fn IInternetProtocol_Terminate(
+    _instance: *mut IInternetProtocolRoot,
+    _dwOptions: DWORD) -> HRESULT
+{
+    S_OK
+}
+
+unsafe extern "system" fn IInternetProtocol_Suspend(
+    _instance: *mut IInternetProtocolRoot) -> HRESULT
+{
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn IInternetProtocol_Resume(
+    _instance: *mut IInternetProtocolRoot) -> HRESULT
+{
+    E_NOTIMPL
+}
+
+unsafe extern "system" fn IInternetProtocol_Read(
+    instance: *mut IInternetProtocol,
+    pv: *mut c_void,
+    cb: ULONG,
+    pcbRead: *mut ULONG) -> HRESULT
+{
+    let protocol = instance as *mut Protocol;
+
+    let remainder = &(*protocol).content[(*protocol).position..];
+    let count = remainder.len().min(cb as usize);
+
+    remainder.as_ptr().copy_to_nonoverlapping(pv as *mut u8, count);
+    (*protocol).position += count;
+
+    if !pcbRead.is_null() {
+        *pcbRead = count as ULONG;
+    }
+
+    if (*protocol).position == (*protocol).content.len() {
+        S_FALSE
+    } else {
+        S_OK
+    }
+}
+
+unsafe extern "system" fn IInternetProtocol_Seek(
+    _instance: *mut IInternetProtocol,
+    _dlibMove: LARGE_INTEGER,
+    _dwOrigin: DWORD,
+    _plibNewPosition: *mut ULARGE_INTEGER) -> HRESULT
+{
+    E_FAIL
+}
+
+unsafe extern "system" fn IInternetProtocol_LockRequest(
+    _instance: *mut IInternetProtocol,
+    _dwOptions: DWORD) -> HRESULT
+{
+    S_OK
+}
+
+unsafe extern "system" fn IInternetProtocol_UnlockRequest(
+    _instance: *mut IInternetProtocol) -> HRESULT
+{
+    S_OK
+}
+
+unsafe extern "system" fn IClassFactory_AddRef(
+    instance: *mut IUnknown) -> ULONG
+{
+    let class_factory = instance as *mut ClassFactory;
+
+    (*class_factory).reference_counter += 1;
+    (*class_factory).reference_counter
+}
+
+unsafe extern "system" fn IClassFactory_Release(
+    instance: *mut IUnknown) -> ULONG
+{
+    let class_factory = instance as *mut ClassFactory;
+
+    let result = {
+        (*class_factory).reference_counter -= 1;
+        (*class_factory).reference_counter
+    };
+
+    assert!(result != ULONG::max_value());
+
+    if result == 0 {
+        drop(Box::from_raw(class_factory));
+    }
+
+    result
+}
+
+unsafe extern "system" fn IClassFactory_QueryInterface(
+    instance: *mut IUnknown,
+    riid: REFIID,
+    ppvObject: *mut *mut c_void) -> HRESULT
+{
+    *ppvObject = if IsEqualGUID(&*riid, &IUnknown::uuidof()) ||
+        IsEqualGUID(&*riid, &IClassFactory::uuidof())
+    {
+        instance as *mut c_void
+    } else {
+        null_mut()
+    };
+
+    if !(*ppvObject).is_null() {
+        (*instance).AddRef();
+        S_OK
+    } else {
+        E_NOINTERFACE
+    }
+}
+
+unsafe extern "system" fn IClassFactory_CreateInstance(
+    instance: *mut IClassFactory,
+    pUnkOuter: *mut IUnknown,
+    riid: REFIID,
+    ppvObject: *mut *mut c_void) -> HRESULT
+{
+    *ppvObject = null_mut();
+
+    if !pUnkOuter.is_null() {
+        return CLASS_E_NOAGGREGATION;
+    }
+
+    let class_factory = instance as *mut ClassFactory;
+
+    let protocol = Box::into_raw(Box::new(
+        Protocol {
+            internet_protocol: IInternetProtocol {
+                lpVtbl: &INTERNET_PROTOCOL_VTABLE,
+            },
+            reference_counter: 1,
+            hosts: (*class_factory).hosts.clone(),
+            content: Cow::Borrowed(&[]),
+            position: 0,
+        })) as *mut IUnknown;
+
+    // The object is destroyed here if it doesn't support the interface.
+    let result = (*protocol).QueryInterface(riid, ppvObject);
+    (*protocol).Release();
+
+    result
+}
+
+unsafe extern "system" fn IClassFactory_LockServer(
+    _instance: *mut IClassFactory,
+    _fLock: BOOL) -> HRESULT
+{
+    S_OK
+}
+
+// The protocol handler registration shared by the windows of the thread.
+// Unregisters the handler when dropped.
+struct Namespace {
+    session: ComPointer<IInternetSession>,
+    class_factory: ComPointer<IClassFactory>,
+    hosts: Rc<Hosts>,
+}
+
+thread_local! {
+    static NAMESPACE: RefCell<Weak<Namespace>> = RefCell::new(Weak::new());
+}
+
+impl Namespace {
+    fn shared() -> Option<Rc<Namespace>> {
+        if let Some(namespace) = NAMESPACE.with(|namespace| {
+            namespace.borrow().upgrade()
+        }) {
+            return Some(namespace);
+        }
+
+        let namespace = Rc::new(Namespace::new()?);
+
+        NAMESPACE.with(|shared| {
+            *shared.borrow_mut() = Rc::downgrade(&namespace);
+        });
+
+        Some(namespace)
+    }
+
+    fn new() -> Option<Namespace> {
+        let mut session = ComPointer::<IInternetSession>::new();
+
+        let success = unsafe {
+            CoInternetGetSession(0, session.as_mut_ptr(), 0) == S_OK
+        };
+
+        if !success || session.get().is_none() {
+            return None;
+        }
+
+        let hosts = Rc::new(Hosts::default());
+
+        let class_factory = ComPointer::from_raw(Box::into_raw(Box::new(
+            ClassFactory {
+                class_factory: IClassFactory {
+                    lpVtbl: &CLASS_FACTORY_VTABLE,
+                },
+                reference_counter: 1,
+                hosts: hosts.clone(),
+            })) as *mut IClassFactory);
+
+        let scheme = wide_string(SCHEME);
+
+        let success = unsafe {
+            session.get().unwrap().RegisterNameSpace(
+                class_factory.as_ptr(),
+                &CLSID_PLUGIN_PROTOCOL,
+                scheme.as_ptr(),
+                0,
+                null_mut(),
+                0) == S_OK
+        };
+
+        match success {
+            true => Some(Namespace { session, class_factory, hosts }),
+            false => None,
+        }
+    }
+}
+
+impl Drop for Namespace {
+    fn drop(&mut self) {
+        let scheme = wide_string(SCHEME);
+
+        unsafe {
+            self.session.get().unwrap().UnregisterNameSpace(
+                self.class_factory.as_ptr(), scheme.as_ptr());
+        }
+    }
+}
+
+// Serves the assets of a single window from its own host while alive.
+pub struct Registration {
+    namespace: Rc<Namespace>,
+    host: String,
+}
+
+impl Registration {
+    pub fn new(assets: Rc<dyn AssetProvider>) -> Option<Registration> {
+        static NEXT_HOST: AtomicUsize = AtomicUsize::new(1);
+
+        let namespace = Namespace::shared()?;
+        let host = format!(
+            "app-{}", NEXT_HOST.fetch_add(1, Ordering::Relaxed));
+
+        namespace.hosts.borrow_mut().insert(host.clone(), assets);
+
+        Some(Registration { namespace, host })
+    }
+
+    // Replaces 'assets::BASE_URL' for the documents of the window.
+    pub fn base_url(&self) -> String {
+        format!("{}://{}/", SCHEME, self.host)
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.namespace.hosts.borrow_mut().remove(&self.host);
+    }
+}